@@ -1,8 +1,281 @@
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Fields, Ident, LitStr};
+
+/// Derives `sealion::Row` and `sealion::RowValues` for a struct with named fields.
+///
+/// Each field maps to the column of the same name. Fields are parsed by column name, so the statement
+/// can select columns in any order. The following field attributes are supported:
+///
+/// - `#[sealion(column = "name")]` maps the field to a differently named column.
+/// - `#[sealion(skip)]` leaves the field out of the columns entirely. It is set to `Default::default()`
+///   when parsing, and isn't written back to the database.
+///
+/// The struct attribute `#[sealion(rename_all = "...")]` converts every field name to the given case to
+/// get its column name, unless it has a `column` attribute. The supported cases are `snake_case`,
+/// `camelCase`, `PascalCase`, `SCREAMING_SNAKE_CASE`, `lowercase` and `UPPERCASE`.
+///
+/// The struct attribute `#[sealion(active_record)]` also generates the inherent methods `find_all`,
+/// `insert`, `update` and `delete`, which update and delete rows by primary key. They require a manual
+/// `sealion::Table` implementation for the struct.
+#[proc_macro_derive(Row, attributes(sealion))]
+pub fn derive_row(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_derive_row(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+#[derive(Clone, Copy)]
+enum RenameRule {
+    Snake,
+    Camel,
+    Pascal,
+    ScreamingSnake,
+    Lower,
+    Upper
+}
+
+impl RenameRule {
+    fn parse(rule: &LitStr) -> syn::Result<Self> {
+        match rule.value().as_str() {
+            "snake_case" => Ok(RenameRule::Snake),
+            "camelCase" => Ok(RenameRule::Camel),
+            "PascalCase" => Ok(RenameRule::Pascal),
+            "SCREAMING_SNAKE_CASE" => Ok(RenameRule::ScreamingSnake),
+            "lowercase" => Ok(RenameRule::Lower),
+            "UPPERCASE" => Ok(RenameRule::Upper),
+            _ => Err(syn::Error::new_spanned(rule, "unsupported rename_all case"))
+        }
+    }
+
+    fn apply(self, name: &str) -> String {
+        let words = split_words(name);
+        match self {
+            RenameRule::Snake => words.join("_"),
+            RenameRule::Camel => words
+                .iter()
+                .enumerate()
+                .map(|(index, word)| if index == 0 { word.clone() } else { capitalize(word) })
+                .collect(),
+            RenameRule::Pascal => words.iter().map(|word| capitalize(word)).collect(),
+            RenameRule::ScreamingSnake => words.join("_").to_uppercase(),
+            RenameRule::Lower => words.concat(),
+            RenameRule::Upper => words.concat().to_uppercase()
+        }
+    }
+}
+
+/// Splits an identifier into lowercase words at underscores and lowercase to uppercase transitions.
+fn split_words(name: &str) -> Vec<String> {
+    let mut words: Vec<String> = Vec::new();
+    let mut previous_lowercase = false;
+
+    for c in name.trim_start_matches("r#").chars() {
+        if c == '_' {
+            previous_lowercase = false;
+            words.push(String::new());
+            continue;
+        }
+        if words.is_empty() || (c.is_uppercase() && previous_lowercase) {
+            words.push(String::new());
+        }
+        previous_lowercase = c.is_lowercase() || c.is_ascii_digit();
+        words.last_mut().expect("a word was pushed").extend(c.to_lowercase());
+    }
+
+    words.retain(|word| !word.is_empty());
+    words
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new()
+    }
+}
+
+/// Attributes applied to the whole struct.
+#[derive(Default)]
+struct RowAttributes {
+    rename_all: Option<RenameRule>,
+    active_record: bool
+}
+
+impl RowAttributes {
+    fn parse(attrs: &[syn::Attribute]) -> syn::Result<Self> {
+        let mut row_attributes = Self::default();
+
+        for attr in attrs.iter().filter(|attr| attr.path().is_ident("sealion")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename_all") {
+                    row_attributes.rename_all = Some(RenameRule::parse(&meta.value()?.parse()?)?);
+                    Ok(())
+                } else if meta.path.is_ident("active_record") {
+                    row_attributes.active_record = true;
+                    Ok(())
+                } else {
+                    Err(meta.error("unsupported sealion struct attribute"))
+                }
+            })?;
+        }
+
+        Ok(row_attributes)
+    }
+}
+
+struct RowField {
+    ident: Ident,
+    column: String,
+    skip: bool
+}
+
+impl RowField {
+    fn parse(field: &syn::Field, row_attributes: &RowAttributes) -> syn::Result<Self> {
+        let ident = field.ident.clone().expect("named fields have identifiers");
+        let name = ident.to_string();
+        let mut row_field = Self {
+            column: match row_attributes.rename_all {
+                Some(rule) => rule.apply(&name),
+                None => name.trim_start_matches("r#").to_string()
+            },
+            ident,
+            skip: false
+        };
+
+        for attr in field.attrs.iter().filter(|attr| attr.path().is_ident("sealion")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("column") {
+                    row_field.column = meta.value()?.parse::<LitStr>()?.value();
+                    Ok(())
+                } else if meta.path.is_ident("skip") {
+                    row_field.skip = true;
+                    Ok(())
+                } else {
+                    Err(meta.error("unsupported sealion field attribute"))
+                }
+            })?;
+        }
+
+        Ok(row_field)
+    }
+}
+
+fn expand_derive_row(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let named_fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => return Err(syn::Error::new_spanned(&input.ident, "Row can only be derived for structs with named fields"))
+        },
+        _ => return Err(syn::Error::new_spanned(&input.ident, "Row can only be derived for structs"))
+    };
+    let row_attributes = RowAttributes::parse(&input.attrs)?;
+    let fields = named_fields
+        .iter()
+        .map(|field| RowField::parse(field, &row_attributes))
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    let name = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+
+    let columns = fields.iter().filter(|field| !field.skip).map(|field| &field.column);
+    let parse_fields = fields.iter().map(|field| {
+        let ident = &field.ident;
+        let column = &field.column;
+        if field.skip {
+            quote! { #ident: ::std::default::Default::default() }
+        } else {
+            quote! { #ident: ::sealion::get_named(row, #column)? }
+        }
+    });
+    let params = fields.iter().filter(|field| !field.skip).map(|field| {
+        let ident = &field.ident;
+        quote! { &self.#ident as &dyn ::sealion::rusqlite::ToSql }
+    });
+
+    let active_record = if row_attributes.active_record {
+        quote! {
+            impl #impl_generics #name #type_generics #where_clause {
+                /// Selects every row of the table. Soft-deleted rows are excluded, see `sealion::Table::select`.
+                pub fn find_all(connection: impl ::std::ops::Deref<Target = ::sealion::rusqlite::Connection>) -> ::std::result::Result<::std::vec::Vec<Self>, ::sealion::SealionError> {
+                    <Self as ::sealion::Table>::select().execute(connection)
+                }
+
+                /// Inserts this row and returns the number of rows inserted.
+                pub fn insert(&self, connection: impl ::std::ops::Deref<Target = ::sealion::rusqlite::Connection>) -> ::std::result::Result<usize, ::sealion::SealionError> {
+                    ::sealion::InsertQuery::for_table::<Self>().execute(connection, self)
+                }
+
+                /// Writes every column of this row to the row with the same primary key, and returns the number
+                /// of rows changed. Fails if a primary key column isn't one of the columns of the row, see
+                /// `sealion::UpdateQuery::set_all`.
+                pub fn update(&self, connection: impl ::std::ops::Deref<Target = ::sealion::rusqlite::Connection>) -> ::std::result::Result<usize, ::sealion::SealionError> {
+                    ::sealion::UpdateQuery::set_all(self)?.execute(connection)
+                }
+
+                /// Deletes the row with the same primary key as this row, and returns the number of rows removed.
+                pub fn delete(&self, connection: impl ::std::ops::Deref<Target = ::sealion::rusqlite::Connection>) -> ::std::result::Result<usize, ::sealion::SealionError> {
+                    ::sealion::DeleteQuery::by_primary_key(self)?.execute(connection)
+                }
+            }
+        }
+    } else {
+        TokenStream2::new()
+    };
+
+    Ok(quote! {
+        #active_record
+
+        impl #impl_generics ::sealion::Row for #name #type_generics #where_clause {
+            fn columns<'a>() -> &'a [&'a str] {
+                &[#(#columns),*]
+            }
+
+            fn parse_row(row: &::sealion::rusqlite::Row) -> ::sealion::rusqlite::Result<Self> {
+                Ok(Self {
+                    #(#parse_fields),*
+                })
+            }
+        }
+
+        impl #impl_generics ::sealion::RowValues for #name #type_generics #where_clause {
+            fn to_params(&self) -> ::std::vec::Vec<&dyn ::sealion::rusqlite::ToSql> {
+                vec![#(#params),*]
+            }
+        }
+    })
+}
+
 #[cfg(test)]
 mod tests {
+    use crate::RenameRule;
+
     #[test]
     fn it_works() {
         let result = 2 + 2;
         assert_eq!(result, 4);
     }
+
+    #[test]
+    fn rename_rules_parse_serde_names() {
+        let parse = |rule: &str| RenameRule::parse(&syn::LitStr::new(rule, proc_macro2::Span::call_site())).is_ok();
+        assert!(parse("PascalCase"));
+        assert!(parse("snake_case"));
+        assert!(!parse("Pascal"));
+        assert!(!parse("kebab-case"));
+    }
+
+    #[test]
+    fn rename_rules() {
+        assert_eq!(RenameRule::Snake.apply("itemHistoryId"), "item_history_id");
+        assert_eq!(RenameRule::Snake.apply("item_history_id"), "item_history_id");
+        assert_eq!(RenameRule::Camel.apply("item_history_id"), "itemHistoryId");
+        assert_eq!(RenameRule::Pascal.apply("item_history_id"), "ItemHistoryId");
+        assert_eq!(RenameRule::ScreamingSnake.apply("itemHistoryId"), "ITEM_HISTORY_ID");
+        assert_eq!(RenameRule::Lower.apply("item_history_id"), "itemhistoryid");
+        assert_eq!(RenameRule::Upper.apply("item_history_id"), "ITEMHISTORYID");
+        assert_eq!(RenameRule::Camel.apply("r#type"), "type");
+    }
 }
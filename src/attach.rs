@@ -0,0 +1,49 @@
+use std::ops::Deref;
+use rusqlite::Connection;
+
+use crate::cache::prepare_cached;
+use crate::SealionResult;
+
+/// Attaches the database file at `path` to the connection as the schema `alias`.
+///
+/// Its tables can then be queried by qualifying them with the alias, e.g. `SelectQuery::new("archive.orders")`.
+/// A `path` of `:memory:` attaches a new in-memory database.
+pub fn attach(connection: impl Deref<Target = Connection>, path: &str, alias: &str) -> SealionResult<()> {
+    let mut statement = prepare_cached(&connection, "ATTACH DATABASE ? AS ?")?;
+    statement.execute([path, alias])?;
+    Ok(())
+}
+
+/// Detaches the database attached as `alias`. See `attach`.
+pub fn detach(connection: impl Deref<Target = Connection>, alias: &str) -> SealionResult<()> {
+    let mut statement = prepare_cached(&connection, "DETACH DATABASE ?")?;
+    statement.execute([alias])?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{attach, detach, SealionResult, SelectQuery};
+    use crate::tests::{setup_test_db, TestRow};
+
+    #[test]
+    fn select_from_attached_database() -> SealionResult<()> {
+        let connection = setup_test_db()?;
+
+        attach(&connection, ":memory:", "other")?;
+        connection.execute_batch("
+            CREATE TABLE other.test_table (id INTEGER PRIMARY KEY, name TEXT NOT NULL, optional TEXT);
+            INSERT INTO other.test_table (id, name) VALUES (7, 'Kiwi');
+        ")?;
+
+        let rows: Vec<TestRow> = SelectQuery::new("other.test_table").execute(&connection)?;
+        assert_eq!(rows, vec![TestRow { id: 7, name: "Kiwi".to_string(), optional: None }]);
+        assert_eq!(SelectQuery::new("test_table").count(&connection)?, 3);
+
+        detach(&connection, "other")?;
+        assert!(SelectQuery::new("other.test_table").count(&connection).is_err());
+        assert!(detach(&connection, "other").is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,76 @@
+use std::thread;
+use std::time::Duration;
+use rusqlite::Connection;
+
+use crate::{SealionError, SealionResult};
+
+/// Makes `connection` wait up to `timeout` for a lock held by another connection before failing
+/// with `SealionError::Busy`.
+pub fn set_busy_timeout(connection: &Connection, timeout: Duration) -> SealionResult<()> {
+    Ok(connection.busy_timeout(timeout)?)
+}
+
+/// Registers a handler that is called with the number of previous attempts whenever `connection`
+/// hits a lock. The handler returns `true` to try again, or `false` to give up with
+/// `SealionError::Busy`. Passing `None` removes the handler.
+///
+/// This replaces any busy timeout set on the connection, and vice versa.
+pub fn set_busy_handler(connection: &Connection, handler: Option<fn(i32) -> bool>) -> SealionResult<()> {
+    Ok(connection.busy_handler(handler)?)
+}
+
+/// Calls `f` until it succeeds, fails with an error other than `SealionError::Busy`, or has been
+/// called `attempts` times.
+///
+/// Retries are spaced out starting with `backoff`, which doubles after every attempt. The error of the
+/// last attempt is returned if all of them fail.
+pub fn retry_busy<T>(attempts: u32, backoff: Duration, mut f: impl FnMut() -> SealionResult<T>) -> SealionResult<T> {
+    let mut delay = backoff;
+
+    for _ in 1..attempts {
+        match f() {
+            Err(SealionError::Busy(_)) => {
+                thread::sleep(delay);
+                delay *= 2;
+            },
+            result => return result
+        }
+    }
+
+    f()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::time::Duration;
+    use rusqlite::Connection;
+
+    use crate::{retry_busy, set_busy_timeout, SealionError, SealionResult, SelectQuery};
+    use crate::tests::{TempDatabase, TestRow};
+
+    #[test]
+    fn busy_database_is_retried() -> SealionResult<()> {
+        let database = TempDatabase::new("busy");
+        let locking_connection = Connection::open(&database.path)?;
+        locking_connection.execute_batch("CREATE TABLE test_table (id INTEGER PRIMARY KEY, name TEXT NOT NULL, optional TEXT);
+            BEGIN EXCLUSIVE;")?;
+
+        let connection = Connection::open(&database.path)?;
+        set_busy_timeout(&connection, Duration::ZERO)?;
+
+        let attempts = Cell::new(0);
+        let result = retry_busy(3, Duration::from_millis(1), || {
+            attempts.set(attempts.get() + 1);
+            SelectQuery::new("test_table").execute::<TestRow>(&connection)
+        });
+        assert!(matches!(result, Err(SealionError::Busy(_))));
+        assert_eq!(attempts.get(), 3);
+
+        locking_connection.execute_batch("COMMIT")?;
+        let rows = SelectQuery::new("test_table").execute_with_retry::<TestRow>(&connection, 3, Duration::from_millis(1))?;
+        assert!(rows.is_empty());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,134 @@
+use std::collections::HashSet;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use log::warn;
+use rusqlite::{CachedStatement, Connection};
+
+/// The number of distinct statements after which a warning is logged, unless changed with
+/// `set_statement_warning_threshold`.
+pub const DEFAULT_STATEMENT_WARNING_THRESHOLD: usize = 1000;
+
+/// Statistics about the statements sealion has prepared. See `statement_stats`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StatementStats {
+    /// The number of distinct SQL strings prepared while tracking was enabled, since the last reset.
+    ///
+    /// Counting stops once the warning threshold is crossed, so this never exceeds `warning_threshold + 1`.
+    pub distinct_statements: usize,
+    /// The number of distinct statements after which a warning is logged.
+    pub warning_threshold: usize
+}
+
+struct StatementTracker {
+    /// Hashes of the SQL strings that have been prepared, to avoid holding on to every string.
+    statements: HashSet<u64>,
+    warning_threshold: usize
+}
+
+/// Whether `prepare_cached` records statements. Checked before touching the tracker, so that untracked
+/// statements don't hash their SQL or take the lock.
+static TRACKING: AtomicBool = AtomicBool::new(false);
+
+fn tracker() -> &'static Mutex<StatementTracker> {
+    static TRACKER: OnceLock<Mutex<StatementTracker>> = OnceLock::new();
+    TRACKER.get_or_init(|| Mutex::new(StatementTracker {
+        statements: HashSet::new(),
+        warning_threshold: DEFAULT_STATEMENT_WARNING_THRESHOLD
+    }))
+}
+
+/// Starts or stops recording the statements sealion prepares, across all connections. Tracking is off by
+/// default, since recording a statement takes a process-wide lock.
+pub fn set_statement_tracking(enabled: bool) {
+    TRACKING.store(enabled, Ordering::Relaxed);
+}
+
+/// Returns statistics about the statements sealion has prepared while tracking was enabled with
+/// `set_statement_tracking`, across all connections.
+///
+/// Statements are kept in each connection's statement cache, so a steadily growing number of distinct
+/// statements usually means values are being formatted into the SQL (for example in a raw WHERE clause)
+/// instead of being bound as parameters.
+pub fn statement_stats() -> StatementStats {
+    let tracker = tracker().lock().unwrap_or_else(|err| err.into_inner());
+    StatementStats {
+        distinct_statements: tracker.statements.len(),
+        warning_threshold: tracker.warning_threshold
+    }
+}
+
+/// Sets the number of distinct statements after which a warning is logged.
+pub fn set_statement_warning_threshold(threshold: usize) {
+    tracker().lock().unwrap_or_else(|err| err.into_inner()).warning_threshold = threshold;
+}
+
+/// Forgets every statement recorded so far, so counting starts again and the warning can be logged again.
+pub fn reset_statement_stats() {
+    tracker().lock().unwrap_or_else(|err| err.into_inner()).statements.clear();
+}
+
+/// Prepares `sql` through the connection's statement cache, recording it in the statement stats if tracking is
+/// enabled.
+pub(crate) fn prepare_cached<'conn>(connection: &'conn Connection, sql: &str) -> rusqlite::Result<CachedStatement<'conn>> {
+    if TRACKING.load(Ordering::Relaxed) {
+        record_statement(sql);
+    }
+
+    connection.prepare_cached(sql)
+}
+
+fn record_statement(sql: &str) {
+    let mut hasher = DefaultHasher::new();
+    sql.hash(&mut hasher);
+
+    let mut tracker = tracker().lock().unwrap_or_else(|err| err.into_inner());
+    // Once the threshold is crossed the warning has been logged, and recording more would only grow the set
+    if tracker.statements.len() > tracker.warning_threshold {
+        return;
+    }
+    if tracker.statements.insert(hasher.finish()) && tracker.statements.len() > tracker.warning_threshold {
+        warn!(target: "sealion_statement_cache",
+            "More than {} distinct statements have been prepared. Are values being formatted into SQL instead of bound as parameters?",
+            tracker.warning_threshold)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{reset_statement_stats, set_statement_tracking, statement_stats, SealionResult, SelectQuery};
+    use crate::tests::{setup_test_db, TestRow};
+
+    /// Turns statement tracking on for as long as it's alive, so other tests aren't recorded afterwards.
+    struct TrackingGuard;
+
+    impl TrackingGuard {
+        fn enable() -> Self {
+            set_statement_tracking(true);
+            TrackingGuard
+        }
+    }
+
+    impl Drop for TrackingGuard {
+        fn drop(&mut self) {
+            set_statement_tracking(false);
+            reset_statement_stats();
+        }
+    }
+
+    #[test]
+    fn distinct_statements_are_counted() -> SealionResult<()> {
+        let connection = setup_test_db()?;
+        let _tracking = TrackingGuard::enable();
+
+        // Other tests may prepare statements while tracking is on, so only a lower bound can be checked. The
+        // statements below are only ever prepared by this test.
+        let before = statement_stats().distinct_statements;
+        SelectQuery::new("test_table").r#where("id = 1 AND 'cache_stats' = 'cache_stats'").execute::<TestRow>(&connection)?;
+        SelectQuery::new("test_table").r#where("id = 2 AND 'cache_stats' = 'cache_stats'").execute::<TestRow>(&connection)?;
+        assert!(statement_stats().distinct_statements >= before + 2);
+
+        Ok(())
+    }
+}
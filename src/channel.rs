@@ -0,0 +1,89 @@
+use std::sync::mpsc::{sync_channel, Receiver};
+use std::thread;
+use rusqlite::{Connection, params_from_iter};
+use rusqlite::types::Value;
+
+use crate::cache::prepare_cached;
+use crate::{check_columns, to_value, Row, SealionError, SealionResult, SelectQuery};
+
+impl SelectQuery {
+    /// Runs the query on a new thread that owns `connection`, and sends every parsed row over a channel holding
+    /// at most `capacity` rows.
+    ///
+    /// The thread waits whenever the channel is full, so rows are read no faster than they're received. It
+    /// stops after sending the first error, like `execute` would fail with, or as soon as the receiver is
+    /// dropped, and the connection is closed once it's done. The query is built before the thread is spawned,
+    /// so changing it afterwards has no effect.
+    pub fn execute_channel<R: Row + Send + 'static>(&self, connection: Connection, capacity: usize) -> Receiver<SealionResult<R>> {
+        let (sender, receiver) = sync_channel(capacity);
+        let send_rows = self.row_sender::<R>();
+        thread::spawn(move || send_rows(&connection, &mut |row| sender.send(row).is_ok()));
+        receiver
+    }
+
+    /// Builds the query, and returns a function that runs it and passes every parsed row to `send` until it
+    /// returns false. The first error, like `execute` would fail with, is passed as the last row.
+    ///
+    /// The function owns everything it needs, so it can be moved to the thread reading the rows.
+    pub(crate) fn row_sender<R: Row + 'static>(&self) -> impl FnOnce(&Connection, &mut dyn FnMut(SealionResult<R>) -> bool) + Send + 'static {
+        let hooked = self.hook_conditions();
+        let sql_string = self.build_sql_string_with(&self.selected_columns(R::columns()), &hooked);
+        // The parameters are converted to owned values because the conditions can't be sent to the thread
+        let params = self.params(&hooked).into_iter().map(to_value).collect::<rusqlite::Result<Vec<Value>>>();
+
+        move |connection, send| {
+            let result = (|| -> SealionResult<()> {
+                let mut statement = prepare_cached(connection, &sql_string)?;
+                check_columns(&statement, R::columns());
+
+                let mut rows = statement.query(params_from_iter(params?))?;
+                let mut row_index = 0;
+                while let Some(row) = rows.next()? {
+                    let value = R::parse_row(row).map_err(|err| SealionError::ParseError { row: row_index, source: Box::new(SealionError::from(err)) })?;
+                    if !send(Ok(value)) {
+                        return Ok(());
+                    }
+                    row_index += 1;
+                }
+                Ok(())
+            })();
+
+            if let Err(err) = result {
+                // The receiver may have been dropped already, in which case there's no one to report to
+                send(Err(err));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rusqlite::types::Value;
+
+    use crate::{Condition, Operator, SealionError, SealionResult, SelectQuery};
+    use crate::tests::{setup_test_db, TestRow};
+
+    #[test]
+    fn rows_are_sent_over_a_channel() -> SealionResult<()> {
+        let connection = setup_test_db()?;
+        connection.execute_batch("
+            WITH RECURSIVE ids(id) AS (SELECT 3 UNION ALL SELECT id + 1 FROM ids WHERE id < 99)
+            INSERT INTO test_table (id, name) SELECT id, 'Fruit' FROM ids;
+        ")?;
+
+        let mut query = SelectQuery::new("test_table");
+        query.and_where(Condition::compare("id", Operator::Gt, Value::Integer(1)));
+        let receiver = query.execute_channel::<TestRow>(connection, 4);
+
+        let ids: Vec<u64> = receiver.iter().map(|row| row.map(|row| row.id)).collect::<SealionResult<_>>()?;
+        assert_eq!(ids, (2..100).collect::<Vec<_>>());
+
+        let connection = setup_test_db()?;
+        connection.execute("UPDATE test_table SET id = -1 WHERE name = 'Apple'", [])?;
+        let results: Vec<SealionResult<TestRow>> = SelectQuery::new("test_table").execute_channel(connection, 1).iter().collect();
+        assert_eq!(results.len(), 1);
+        assert!(matches!(results[0], Err(SealionError::ParseError { row: 0, .. })));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,308 @@
+use std::ops::Not;
+use std::str::FromStr;
+use rusqlite::ToSql;
+use rusqlite::types::Value;
+
+use crate::{quote_ident, SealionError, SealionResult, SelectQuery};
+
+/// A comparison operator for conditions built at runtime. See `Condition::compare`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operator {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Like,
+    Glob
+}
+
+impl Operator {
+    pub fn as_sql(&self) -> &'static str {
+        match self {
+            Operator::Eq => "=",
+            Operator::Ne => "!=",
+            Operator::Lt => "<",
+            Operator::Le => "<=",
+            Operator::Gt => ">",
+            Operator::Ge => ">=",
+            Operator::Like => "LIKE",
+            Operator::Glob => "GLOB"
+        }
+    }
+}
+
+impl FromStr for Operator {
+    type Err = SealionError;
+
+    /// Parses an operator from its SQL form, e.g. `>=` or `like`. Fails with `SealionError::UnknownOperator`
+    /// for anything else.
+    fn from_str(operator: &str) -> Result<Self, Self::Err> {
+        match operator.trim().to_ascii_uppercase().as_str() {
+            "=" | "==" => Ok(Operator::Eq),
+            "!=" | "<>" => Ok(Operator::Ne),
+            "<" => Ok(Operator::Lt),
+            "<=" => Ok(Operator::Le),
+            ">" => Ok(Operator::Gt),
+            ">=" => Ok(Operator::Ge),
+            "LIKE" => Ok(Operator::Like),
+            "GLOB" => Ok(Operator::Glob),
+            _ => Err(SealionError::UnknownOperator(operator.to_string()))
+        }
+    }
+}
+
+/// The most values `Condition::in_array` puts in a single `IN (...)` list.
+const IN_CHUNK_SIZE: usize = 999;
+
+/// A condition for a WHERE clause, carrying the parameters bound by its placeholders.
+pub struct Condition {
+    pub sql: String,
+    pub params: Vec<Box<dyn ToSql>>
+}
+
+impl Condition {
+    /// Creates a condition from raw SQL that doesn't bind any parameters.
+    pub fn new<S: ToString>(sql: S) -> Self {
+        Self {
+            sql: sql.to_string(),
+            params: Vec::new()
+        }
+    }
+
+    /// Matches rows where `column` equals `value`.
+    pub fn eq<T: ToSql + 'static>(column: &str, value: T) -> Self {
+        Self {
+            sql: format!("{} = ?", quote_ident(column)),
+            params: vec![Box::new(value)]
+        }
+    }
+
+    /// Matches rows where `column` equals `value`, or where `column` is NULL if `value` is `None`.
+    ///
+    /// Binding `None` with `eq` renders `column = NULL`, which never matches anything. This avoids that
+    /// by switching to `column IS NULL`.
+    pub fn opt_eq<T: ToSql + 'static>(column: &str, value: Option<T>) -> Self {
+        match value {
+            Some(value) => Self::eq(column, value),
+            None => Self::is_null(column)
+        }
+    }
+
+    /// Matches rows where `column` is `value`, rendering as `column IS ?`.
+    ///
+    /// Unlike `eq`, this is NULL-safe: binding NULL matches rows where `column` is NULL, and otherwise NULL
+    /// columns don't match rather than making the comparison NULL.
+    pub fn is<T: ToSql + 'static>(column: &str, value: T) -> Self {
+        Self {
+            sql: format!("{} IS ?", quote_ident(column)),
+            params: vec![Box::new(value)]
+        }
+    }
+
+    /// Matches rows where `column` is not `value`, rendering as `column IS NOT ?`. See `is`.
+    pub fn is_not<T: ToSql + 'static>(column: &str, value: T) -> Self {
+        Self {
+            sql: format!("{} IS NOT ?", quote_ident(column)),
+            params: vec![Box::new(value)]
+        }
+    }
+
+    /// Matches rows where `column` matches the GLOB `pattern`.
+    ///
+    /// Unlike LIKE, GLOB is case-sensitive and uses Unix wildcards: `*` for any run of characters, `?` for
+    /// a single character and `[...]` for a set of characters.
+    pub fn glob<T: ToSql + 'static>(column: &str, pattern: T) -> Self {
+        Self {
+            sql: format!("{} GLOB ?", quote_ident(column)),
+            params: vec![Box::new(pattern)]
+        }
+    }
+
+    /// Matches rows where `column` compares to `value` with `operator`.
+    ///
+    /// This is meant for filters that are only known at runtime, such as ones coming from user input. As
+    /// with `opt_eq`, comparing for (in)equality with NULL renders as `IS NULL` or `IS NOT NULL`.
+    pub fn compare(column: &str, operator: Operator, value: Value) -> Self {
+        match (operator, value) {
+            (Operator::Eq, Value::Null) => Self::is_null(column),
+            (Operator::Ne, Value::Null) => Self::is_not_null(column),
+            (operator, value) => Self {
+                sql: format!("{} {} ?", quote_ident(column), operator.as_sql()),
+                params: vec![Box::new(value)]
+            }
+        }
+    }
+
+    /// Matches rows where `column` is one of the values selected by `subquery`, which is rendered as a
+    /// nested SELECT with its parameters bound along with the rest of the query.
+    ///
+    /// `subquery` must select exactly one expression with `SelectQuery::select_exprs`, otherwise this fails
+    /// with `SealionError::InvalidQuery`.
+    pub fn in_subquery(column: &str, subquery: SelectQuery) -> SealionResult<Self> {
+        if subquery.select_exprs.len() != 1 {
+            return Err(SealionError::InvalidQuery(format!(
+                "subquery must select exactly one column, not {}",
+                subquery.select_exprs.len()
+            )));
+        }
+
+        let hooked = subquery.hook_conditions();
+        let sql = format!("{} IN ({})", quote_ident(column), subquery.build_sql_string_with(&subquery.selected_columns(&[]), &hooked).trim_end());
+        let params = subquery.select_params
+            .into_iter()
+            .chain(subquery.conditions.into_iter().chain(hooked).chain(subquery.having_conditions).flat_map(|condition| condition.params))
+            .collect();
+
+        Ok(Self { sql, params })
+    }
+
+    /// Matches rows where `column` is one of `values`.
+    ///
+    /// Each value gets its own placeholder. Lists of more than 999 values are split into `IN (...)` groups of at
+    /// most 999 joined with OR. The statement as a whole still counts against SQLite's limit on the number of
+    /// parameters (32766 by default), so very long lists should use `in_rarray` instead.
+    pub fn in_array(column: &str, values: Vec<Value>) -> Self {
+        if values.len() <= IN_CHUNK_SIZE {
+            return Self {
+                sql: format!("{} IN ({})", quote_ident(column), vec!["?"; values.len()].join(", ")),
+                params: values.into_iter().map(|value| Box::new(value) as Box<dyn ToSql>).collect()
+            };
+        }
+
+        let groups = values.chunks(IN_CHUNK_SIZE).map(|chunk| Self::in_array(column, chunk.to_vec())).collect();
+        Self::or(groups)
+    }
+
+    /// Matches rows where `column` is one of `values`, rendering as `column IN rarray(?)`.
+    ///
+    /// Every value is bound as a single parameter, so lists of any length use one statement and one
+    /// placeholder. The `rarray` table-valued function has to be registered on each connection first, with
+    /// `rusqlite::vtab::array::load_module`, otherwise the statement fails to prepare.
+    #[cfg(feature = "array")]
+    pub fn in_rarray(column: &str, values: Vec<Value>) -> Self {
+        Self {
+            sql: format!("{} IN rarray(?)", quote_ident(column)),
+            params: vec![Box::new(std::rc::Rc::new(values))]
+        }
+    }
+
+    /// Matches rows matching every one of `conditions`, rendering as `(a) AND (b) ...`.
+    pub fn and(conditions: Vec<Condition>) -> Self {
+        Self::join(conditions, " AND ")
+    }
+
+    /// Matches rows matching any of `conditions`, rendering as `(a) OR (b) ...`.
+    pub fn or(conditions: Vec<Condition>) -> Self {
+        Self::join(conditions, " OR ")
+    }
+
+    fn join(conditions: Vec<Condition>, separator: &str) -> Self {
+        let sql: Vec<String> = conditions.iter().map(|condition| format!("({})", condition.sql)).collect();
+        Self {
+            sql: sql.join(separator),
+            params: conditions.into_iter().flat_map(|condition| condition.params).collect()
+        }
+    }
+
+    /// Matches rows that don't match `inner`, rendering as `NOT (inner)`. Also available as `!inner`.
+    #[allow(clippy::should_implement_trait)]
+    pub fn not(inner: Condition) -> Self {
+        Self {
+            sql: format!("NOT ({})", inner.sql),
+            params: inner.params
+        }
+    }
+
+    /// Matches rows where the value at the JSON `path` of `column` equals `value`, rendering as
+    /// `json_extract(column, 'path') = ?`. See `json_extract`.
+    pub fn json_extract_eq<T: ToSql + 'static>(column: &str, path: &str, value: T) -> SealionResult<Self> {
+        Ok(Self {
+            sql: format!("{} = ?", json_extract(column, path)?),
+            params: vec![Box::new(value)]
+        })
+    }
+
+    /// Matches rows where `column` is NULL.
+    ///
+    /// This renders as `column IS NULL`. Note that `column = NULL` is never true in SQL, not even
+    /// for NULL values.
+    pub fn is_null(column: &str) -> Self {
+        Self::new(format!("{} IS NULL", quote_ident(column)))
+    }
+
+    /// Matches rows where `column` is not NULL.
+    pub fn is_not_null(column: &str) -> Self {
+        Self::new(format!("{} IS NOT NULL", quote_ident(column)))
+    }
+}
+
+impl Not for Condition {
+    type Output = Condition;
+
+    fn not(self) -> Self::Output {
+        Condition::not(self)
+    }
+}
+
+/// Renders a call to SQLite's `json_extract` reading the JSON `path` of `column`, e.g. `$.address.city`.
+///
+/// The path is embedded as a string literal rather than bound, so that SQLite can use an index on the same
+/// expression. Fails with `SealionError::InvalidQuery` if `path` doesn't start with `$`.
+pub(crate) fn json_extract(column: &str, path: &str) -> SealionResult<String> {
+    if !path.starts_with('$') {
+        return Err(SealionError::InvalidQuery(format!("JSON path {} doesn't start with $", path)));
+    }
+    Ok(format!("json_extract({}, '{}')", quote_ident(column), path.replace('\'', "''")))
+}
+
+/// Renders `conditions` with numbered `?NNN` placeholders in place of their anonymous `?` ones, binding
+/// equal parameters to the same number so each distinct value is bound once.
+///
+/// Returns the rendered conditions and the parameters to bind, in the order of their numbers. Placeholders
+/// inside quoted strings and identifiers are left alone, as are placeholders that are already numbered or
+/// named.
+pub(crate) fn number_placeholders<'a, I: IntoIterator<Item = &'a Condition>>(conditions: I) -> (Vec<String>, Vec<&'a dyn ToSql>) {
+    let mut distinct: Vec<(Option<Value>, &dyn ToSql)> = Vec::new();
+
+    let rendered = conditions
+        .into_iter()
+        .map(|condition| {
+            let mut params = condition.params.iter().map(|param| param.as_ref());
+            let mut sql = String::with_capacity(condition.sql.len());
+            let mut closing_quote = None;
+            let mut chars = condition.sql.chars().peekable();
+
+            while let Some(c) = chars.next() {
+                match (closing_quote, c) {
+                    (Some(quote), c) if c == quote => closing_quote = None,
+                    (Some(_), _) => {},
+                    (None, '\'' | '"' | '`') => closing_quote = Some(c),
+                    (None, '[') => closing_quote = Some(']'),
+                    (None, '?') if !chars.peek().is_some_and(char::is_ascii_digit) => {
+                        if let Some(param) = params.next() {
+                            // Parameters that can't be converted are never shared
+                            let value = crate::to_value(param).ok();
+                            let index = match distinct.iter().position(|(other, _)| value.is_some() && *other == value) {
+                                Some(index) => index,
+                                None => {
+                                    distinct.push((value, param));
+                                    distinct.len() - 1
+                                }
+                            };
+                            sql.push_str(&format!("?{}", index + 1));
+                            continue;
+                        }
+                    },
+                    _ => {}
+                }
+                sql.push(c);
+            }
+
+            sql
+        })
+        .collect();
+
+    (rendered, distinct.into_iter().map(|(_, param)| param).collect())
+}
@@ -0,0 +1,692 @@
+use std::ops::Deref;
+use rusqlite::Connection;
+
+use crate::cache::prepare_cached;
+use crate::{quote_ident, ConflictResolution, SealionError, SealionResult, Table};
+
+/// How the value of a generated column is kept.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GeneratedStorage {
+    /// The value is computed when the row is written and stored in the table.
+    Stored,
+    /// The value is computed whenever the column is read.
+    Virtual
+}
+
+/// The definition of a column in a CREATE TABLE statement.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ColumnDef {
+    pub name: String,
+    /// The declared type of the column. May be empty, in which case the column has no declared type.
+    pub sql_type: String,
+    pub nullable: bool,
+    pub primary_key: bool,
+    pub unique: bool,
+    /// How SQLite resolves violations of the UNIQUE constraint of the column, unless an insert overrides it with
+    /// `InsertQuery::or`. Ignored if the column isn't `unique`.
+    pub unique_on_conflict: Option<ConflictResolution>,
+    /// The expression SQLite fills the column in with when an insert leaves it out.
+    pub default: Option<String>,
+    /// The expression the column is generated from, if it is a generated column.
+    pub generated: Option<(String, GeneratedStorage)>
+}
+
+impl ColumnDef {
+    /// Creates a definition of a nullable column.
+    pub fn new<S: ToString, T: ToString>(name: S, sql_type: T) -> Self {
+        Self {
+            name: name.to_string(),
+            sql_type: sql_type.to_string(),
+            nullable: true,
+            primary_key: false,
+            unique: false,
+            unique_on_conflict: None,
+            default: None,
+            generated: None
+        }
+    }
+
+    pub fn not_null(mut self) -> Self {
+        self.nullable = false;
+        self
+    }
+
+    pub fn primary_key(mut self) -> Self {
+        self.primary_key = true;
+        self
+    }
+
+    pub fn unique(mut self) -> Self {
+        self.unique = true;
+        self
+    }
+
+    /// Makes the column unique, resolving violations with `resolution`, e.g. `UNIQUE ON CONFLICT REPLACE`.
+    ///
+    /// This bakes the resolution into the schema, so every insert uses it without needing `InsertQuery::or`. The
+    /// PRIMARY KEY and NOT NULL constraints of the column keep the default resolution.
+    pub fn unique_on_conflict(mut self, resolution: ConflictResolution) -> Self {
+        self.unique = true;
+        self.unique_on_conflict = Some(resolution);
+        self
+    }
+
+    /// Gives the column a default value of `expression`, e.g. `0` or `CURRENT_TIMESTAMP`.
+    ///
+    /// To have SQLite apply the default, leave the column out of the insert with `InsertQuery::omit`.
+    pub fn default<S: ToString>(mut self, expression: S) -> Self {
+        self.default = Some(expression.to_string());
+        self
+    }
+
+    /// Makes this a generated column, computed from `expression`.
+    ///
+    /// Generated columns can't be written to, so they are left out of inserts made with
+    /// `InsertQuery::for_table`.
+    pub fn generated_as<S: ToString>(mut self, expression: S, storage: GeneratedStorage) -> Self {
+        self.generated = Some((expression.to_string(), storage));
+        self
+    }
+
+    pub fn build_sql_string(&self) -> String {
+        let mut sql_string = quote_ident(&self.name);
+
+        if !self.sql_type.is_empty() {
+            sql_string.push(' ');
+            sql_string.push_str(&self.sql_type);
+        }
+        if self.primary_key {
+            sql_string.push_str(" PRIMARY KEY");
+        }
+        if !self.nullable {
+            sql_string.push_str(" NOT NULL");
+        }
+        if self.unique {
+            sql_string.push_str(" UNIQUE");
+            if let Some(resolution) = self.unique_on_conflict {
+                sql_string.push_str(&format!(" ON CONFLICT {}", resolution.as_sql()));
+            }
+        }
+        if let Some(expression) = &self.default {
+            sql_string.push_str(&format!(" DEFAULT {}", expression));
+        }
+        if let Some((expression, storage)) = &self.generated {
+            let storage = match storage {
+                GeneratedStorage::Stored => "STORED",
+                GeneratedStorage::Virtual => "VIRTUAL"
+            };
+            sql_string.push_str(&format!(" GENERATED ALWAYS AS ({}) {}", expression, storage));
+        }
+
+        sql_string
+    }
+}
+
+/// Builds the CREATE TABLE statement for `R` from `Table::column_defs`.
+///
+/// If `Table::primary_key` has more than one column, the key is declared with a `PRIMARY KEY (a, b)` table
+/// constraint after the columns, and `ColumnDef::primary_key` is ignored.
+pub fn create_table_sql<R: Table>() -> String {
+    let primary_key = R::primary_key();
+    let composite_key = primary_key.len() > 1;
+
+    let mut definitions: Vec<String> = R::column_defs()
+        .into_iter()
+        .map(|column_def| ColumnDef { primary_key: column_def.primary_key && !composite_key, ..column_def }.build_sql_string())
+        .collect();
+    if composite_key {
+        let columns: Vec<String> = primary_key.iter().map(|column| quote_ident(column)).collect();
+        definitions.push(format!("PRIMARY KEY ({})", columns.join(", ")));
+    }
+
+    format!("CREATE TABLE {} ({})", quote_ident(R::table_name()), definitions.join(", "))
+}
+
+/// Creates the table for `R`. See `create_table_sql`.
+pub fn create_table<R: Table>(connection: impl Deref<Target = Connection>) -> SealionResult<()> {
+    connection.execute(&create_table_sql::<R>(), [])?;
+    Ok(())
+}
+
+/// Reads the definitions of the columns of `table_name` from the database with `PRAGMA table_info`.
+///
+/// This only reports what `table_info` does, so `unique` is always false and `unique_on_conflict` and `generated`
+/// are always `None`. Returns an empty list if there is no such table.
+pub fn introspect_table(connection: impl Deref<Target = Connection>, table_name: &str) -> SealionResult<Vec<ColumnDef>> {
+    let mut statement = prepare_cached(&connection, r#"SELECT name, type, "notnull", dflt_value, pk FROM pragma_table_info(?) ORDER BY cid"#)?;
+    let column_defs = statement.query_map([table_name], |row| Ok(ColumnDef {
+            name: row.get(0)?,
+            sql_type: row.get(1)?,
+            nullable: !row.get::<_, bool>(2)?,
+            primary_key: row.get::<_, i64>(4)? > 0,
+            unique: false,
+            unique_on_conflict: None,
+            default: row.get(3)?,
+            generated: None
+        }))?
+        .collect::<rusqlite::Result<Vec<ColumnDef>>>()?;
+
+    Ok(column_defs)
+}
+
+/// A difference between the columns a `Table` expects and the columns of the live table. See `schema_matches`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SchemaDiff {
+    /// The table has no column with this name.
+    MissingColumn(String),
+    /// The table has a column with this name that the `Table` doesn't expect.
+    ExtraColumn(String),
+    /// The column is declared with a different type than expected.
+    TypeMismatch {
+        column: String,
+        expected: String,
+        actual: String
+    }
+}
+
+/// Compares `R::column_defs()` against the columns of its table in the database, returning every difference.
+/// An empty list means the table is compatible with `R`.
+///
+/// Types are compared case-insensitively, and columns without a declared type in `column_defs` match any
+/// type. Generated columns aren't reported by `PRAGMA table_info`, so they are left out of the comparison.
+pub fn schema_matches<R: Table>(connection: impl Deref<Target = Connection>) -> SealionResult<Vec<SchemaDiff>> {
+    let expected: Vec<ColumnDef> = R::column_defs().into_iter().filter(|column_def| column_def.generated.is_none()).collect();
+    let actual = introspect_table(connection, R::table_name())?;
+    let mut diffs = Vec::new();
+
+    for expected_def in &expected {
+        match actual.iter().find(|actual_def| actual_def.name.eq_ignore_ascii_case(&expected_def.name)) {
+            None => diffs.push(SchemaDiff::MissingColumn(expected_def.name.clone())),
+            Some(actual_def) if !expected_def.sql_type.is_empty() && !expected_def.sql_type.eq_ignore_ascii_case(&actual_def.sql_type) => {
+                diffs.push(SchemaDiff::TypeMismatch {
+                    column: expected_def.name.clone(),
+                    expected: expected_def.sql_type.clone(),
+                    actual: actual_def.sql_type.clone()
+                })
+            },
+            Some(_) => {}
+        }
+    }
+    for actual_def in &actual {
+        if !expected.iter().any(|expected_def| expected_def.name.eq_ignore_ascii_case(&actual_def.name)) {
+            diffs.push(SchemaDiff::ExtraColumn(actual_def.name.clone()));
+        }
+    }
+
+    Ok(diffs)
+}
+
+/// Builds a CREATE INDEX statement.
+pub struct IndexBuilder {
+    pub name: String,
+    pub table_name: String,
+    pub columns: Vec<String>,
+    pub unique: bool,
+    pub if_not_exists: bool,
+    /// The condition of a partial index, which only indexes the rows matching it.
+    pub where_clause: Option<String>
+}
+
+impl IndexBuilder {
+    pub fn new<S: ToString, T: ToString>(name: S, table_name: T) -> Self {
+        Self {
+            name: name.to_string(),
+            table_name: table_name.to_string(),
+            columns: Vec::new(),
+            unique: false,
+            if_not_exists: false,
+            where_clause: None
+        }
+    }
+
+    /// Creates an index on `columns` of the table of `R`.
+    ///
+    /// Fails with `SealionError::UnknownColumns` if any of `columns` is not one of `R::columns()`.
+    pub fn for_table<R: Table, S: ToString>(name: S, columns: &[&str]) -> SealionResult<Self> {
+        let unknown_columns: Vec<String> = columns
+            .iter()
+            .filter(|column| !R::columns().contains(column))
+            .map(|column| column.to_string())
+            .collect();
+        if !unknown_columns.is_empty() {
+            return Err(SealionError::UnknownColumns(unknown_columns));
+        }
+
+        let mut index = Self::new(name, R::table_name());
+        index.columns(columns);
+        Ok(index)
+    }
+
+    /// Sets the indexed columns, replacing any set before.
+    pub fn columns<S: ToString>(&mut self, columns: &[S]) -> &mut Self {
+        self.columns = columns.iter().map(ToString::to_string).collect();
+        self
+    }
+
+    pub fn unique(&mut self) -> &mut Self {
+        self.unique = true;
+        self
+    }
+
+    pub fn if_not_exists(&mut self) -> &mut Self {
+        self.if_not_exists = true;
+        self
+    }
+
+    /// Makes this a partial index, which only indexes rows matching `where_clause`.
+    pub fn r#where<S: ToString>(&mut self, where_clause: S) -> &mut Self {
+        self.where_clause = Some(where_clause.to_string());
+        self
+    }
+
+    pub fn build_sql_string(&self) -> String {
+        let mut sql_string = String::from("CREATE ");
+        if self.unique {
+            sql_string.push_str("UNIQUE ");
+        }
+        sql_string.push_str("INDEX ");
+        if self.if_not_exists {
+            sql_string.push_str("IF NOT EXISTS ");
+        }
+
+        let columns: Vec<String> = self.columns.iter().map(|column| quote_ident(column)).collect();
+        sql_string.push_str(&format!("{} ON {} ({})", quote_ident(&self.name), quote_ident(&self.table_name), columns.join(", ")));
+
+        if let Some(where_string) = &self.where_clause {
+            sql_string.push_str(&format!(" WHERE {}", where_string));
+        }
+
+        sql_string
+    }
+
+    pub fn execute(&self, connection: impl Deref<Target = Connection>) -> SealionResult<()> {
+        connection.execute(&self.build_sql_string(), [])?;
+        Ok(())
+    }
+}
+
+/// When a trigger runs relative to the statement that fires it. See `TriggerBuilder`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TriggerTiming {
+    Before,
+    After,
+    /// Runs instead of the statement. Only allowed on views.
+    InsteadOf
+}
+
+/// The kind of statement that fires a trigger. See `TriggerBuilder`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TriggerEvent {
+    Insert,
+    Update,
+    Delete
+}
+
+/// Builds a CREATE TRIGGER statement.
+pub struct TriggerBuilder {
+    pub name: String,
+    pub table_name: String,
+    pub timing: TriggerTiming,
+    pub event: TriggerEvent,
+    pub if_not_exists: bool,
+    /// The condition rows must match for the trigger to run.
+    pub when: Option<String>,
+    /// The statements run by the trigger, which can refer to the affected row with `NEW` and `OLD`.
+    pub body: String
+}
+
+impl TriggerBuilder {
+    pub fn new<S: ToString, T: ToString>(name: S, table_name: T, timing: TriggerTiming, event: TriggerEvent) -> Self {
+        Self {
+            name: name.to_string(),
+            table_name: table_name.to_string(),
+            timing,
+            event,
+            if_not_exists: false,
+            when: None,
+            body: String::new()
+        }
+    }
+
+    pub fn if_not_exists(&mut self) -> &mut Self {
+        self.if_not_exists = true;
+        self
+    }
+
+    /// Only runs the trigger for rows matching `condition`, e.g. `NEW.price > 0`.
+    pub fn when<S: ToString>(&mut self, condition: S) -> &mut Self {
+        self.when = Some(condition.to_string());
+        self
+    }
+
+    /// Sets the statements run by the trigger. A trailing `;` is added if the body doesn't end with one.
+    pub fn body<S: ToString>(&mut self, body: S) -> &mut Self {
+        self.body = body.to_string();
+        self
+    }
+
+    pub fn build_sql_string(&self) -> String {
+        let mut sql_string = String::from("CREATE TRIGGER ");
+        if self.if_not_exists {
+            sql_string.push_str("IF NOT EXISTS ");
+        }
+
+        let timing = match self.timing {
+            TriggerTiming::Before => "BEFORE",
+            TriggerTiming::After => "AFTER",
+            TriggerTiming::InsteadOf => "INSTEAD OF"
+        };
+        let event = match self.event {
+            TriggerEvent::Insert => "INSERT",
+            TriggerEvent::Update => "UPDATE",
+            TriggerEvent::Delete => "DELETE"
+        };
+        sql_string.push_str(&format!("{} {} {} ON {} FOR EACH ROW ", quote_ident(&self.name), timing, event, quote_ident(&self.table_name)));
+
+        if let Some(condition) = &self.when {
+            sql_string.push_str(&format!("WHEN {} ", condition));
+        }
+
+        let body = self.body.trim();
+        let separator = if body.ends_with(';') { "" } else { ";" };
+        sql_string.push_str(&format!("BEGIN {}{} END", body, separator));
+
+        sql_string
+    }
+
+    pub fn execute(&self, connection: impl Deref<Target = Connection>) -> SealionResult<()> {
+        connection.execute(&self.build_sql_string(), [])?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rusqlite::Connection;
+
+    use crate::{create_table, create_table_sql, introspect_table, schema_matches, ColumnDef, ConflictResolution, DeleteQuery, GeneratedStorage, IndexBuilder, InsertQuery, Row, SchemaDiff, SealionError, SealionResult, Table, TriggerBuilder, TriggerEvent, TriggerTiming};
+
+    #[derive(Row, Debug, PartialEq)]
+    struct Product {
+        id: i64,
+        price: f64,
+        quantity: i64,
+        total: f64
+    }
+
+    impl Table for Product {
+        type Key = i64;
+
+        fn table_name<'a>() -> &'a str {
+            "products"
+        }
+
+        fn column_defs() -> Vec<ColumnDef> {
+            vec![
+                ColumnDef::new("id", "INTEGER").primary_key(),
+                ColumnDef::new("price", "REAL").not_null(),
+                ColumnDef::new("quantity", "INTEGER").not_null(),
+                ColumnDef::new("total", "REAL").generated_as("price * quantity", GeneratedStorage::Stored)
+            ]
+        }
+    }
+
+    #[test]
+    fn create_table_with_generated_column() -> SealionResult<()> {
+        assert_eq!(
+            create_table_sql::<Product>(),
+            r#"CREATE TABLE "products" ("id" INTEGER PRIMARY KEY, "price" REAL NOT NULL, "quantity" INTEGER NOT NULL, "total" REAL GENERATED ALWAYS AS (price * quantity) STORED)"#
+        );
+
+        let connection = Connection::open_in_memory()?;
+        create_table::<Product>(&connection)?;
+
+        let product = Product { id: 1, price: 2.5, quantity: 4, total: 0.0 };
+        InsertQuery::for_table::<Product>().execute(&connection, &product)?;
+
+        let rows: Vec<Product> = Product::select().execute(&connection)?;
+        assert_eq!(rows, vec![Product { id: 1, price: 2.5, quantity: 4, total: 10.0 }]);
+
+        Ok(())
+    }
+
+    #[derive(Row, Debug)]
+    struct Event {
+        id: i64,
+        name: String,
+        created_at: String
+    }
+
+    impl Table for Event {
+        type Key = i64;
+
+        fn table_name<'a>() -> &'a str {
+            "events"
+        }
+
+        fn column_defs() -> Vec<ColumnDef> {
+            vec![
+                ColumnDef::new("id", "INTEGER").primary_key(),
+                ColumnDef::new("name", "TEXT").not_null(),
+                ColumnDef::new("created_at", "TEXT").not_null().default("CURRENT_TIMESTAMP")
+            ]
+        }
+    }
+
+    #[test]
+    fn create_table_with_default() -> SealionResult<()> {
+        assert_eq!(
+            create_table_sql::<Event>(),
+            r#"CREATE TABLE "events" ("id" INTEGER PRIMARY KEY, "name" TEXT NOT NULL, "created_at" TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP)"#
+        );
+
+        let connection = Connection::open_in_memory()?;
+        create_table::<Event>(&connection)?;
+
+        let event = Event { id: 1, name: "Launch".to_string(), created_at: String::new() };
+        InsertQuery::for_table::<Event>().omit("created_at").execute(&connection, &event)?;
+
+        let rows: Vec<Event> = Event::select().execute(&connection)?;
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].name, "Launch");
+        assert!(!rows[0].created_at.is_empty());
+
+        Ok(())
+    }
+
+    #[derive(Row, Debug, PartialEq)]
+    struct Tag {
+        id: i64,
+        name: String
+    }
+
+    impl Table for Tag {
+        type Key = i64;
+
+        fn table_name<'a>() -> &'a str {
+            "tags"
+        }
+
+        fn column_defs() -> Vec<ColumnDef> {
+            vec![
+                ColumnDef::new("id", "INTEGER").primary_key(),
+                ColumnDef::new("name", "TEXT").not_null().unique_on_conflict(ConflictResolution::Replace)
+            ]
+        }
+    }
+
+    #[test]
+    fn create_table_with_conflict_clause() -> SealionResult<()> {
+        assert_eq!(
+            create_table_sql::<Tag>(),
+            r#"CREATE TABLE "tags" ("id" INTEGER PRIMARY KEY, "name" TEXT NOT NULL UNIQUE ON CONFLICT REPLACE)"#
+        );
+
+        let connection = Connection::open_in_memory()?;
+        create_table::<Tag>(&connection)?;
+
+        let insert = InsertQuery::for_table::<Tag>();
+        insert.execute(&connection, &Tag { id: 1, name: "fruit".to_string() })?;
+        insert.execute(&connection, &Tag { id: 2, name: "fruit".to_string() })?;
+
+        let rows: Vec<Tag> = Tag::select().execute(&connection)?;
+        assert_eq!(rows, vec![Tag { id: 2, name: "fruit".to_string() }]);
+
+        Ok(())
+    }
+
+    #[derive(Row, Debug, PartialEq)]
+    struct Membership {
+        user_id: i64,
+        group_id: i64,
+        role: String
+    }
+
+    impl Table for Membership {
+        type Key = i64;
+
+        fn table_name<'a>() -> &'a str {
+            "memberships"
+        }
+
+        fn primary_key<'a>() -> &'a [&'a str] {
+            &["user_id", "group_id"]
+        }
+
+        fn column_defs() -> Vec<ColumnDef> {
+            vec![
+                ColumnDef::new("user_id", "INTEGER").not_null(),
+                ColumnDef::new("group_id", "INTEGER").not_null(),
+                ColumnDef::new("role", "TEXT").not_null()
+            ]
+        }
+    }
+
+    #[test]
+    fn composite_primary_key() -> SealionResult<()> {
+        assert_eq!(
+            create_table_sql::<Membership>(),
+            r#"CREATE TABLE "memberships" ("user_id" INTEGER NOT NULL, "group_id" INTEGER NOT NULL, "role" TEXT NOT NULL, PRIMARY KEY ("user_id", "group_id"))"#
+        );
+
+        let connection = Connection::open_in_memory()?;
+        create_table::<Membership>(&connection)?;
+        let insert = InsertQuery::for_table::<Membership>();
+        insert.execute(&connection, &Membership { user_id: 1, group_id: 1, role: "owner".to_string() })?;
+        insert.execute(&connection, &Membership { user_id: 1, group_id: 2, role: "member".to_string() })?;
+        assert!(insert.execute(&connection, &Membership { user_id: 1, group_id: 2, role: "owner".to_string() }).is_err());
+
+        let membership = Membership::find_by_pk(&connection, (1, 2))?;
+        assert_eq!(membership, Some(Membership { user_id: 1, group_id: 2, role: "member".to_string() }));
+        assert_eq!(Membership::find_by_pk(&connection, (2, 1))?, None);
+        assert!(matches!(Membership::find_by_pk(&connection, (1,)), Err(SealionError::InvalidQuery(_))));
+
+        let rows_removed = DeleteQuery::by_primary_key(&Membership { user_id: 1, group_id: 1, role: String::new() })?.execute(&connection)?;
+        assert_eq!(rows_removed, 1);
+        assert!(matches!(Membership::select().execute_with_keys::<Membership>(&connection), Err(SealionError::InvalidQuery(_))));
+
+        Ok(())
+    }
+
+    #[test]
+    fn introspect_created_table() -> SealionResult<()> {
+        let connection = Connection::open_in_memory()?;
+        create_table::<Event>(&connection)?;
+
+        assert_eq!(introspect_table(&connection, "events")?, Event::column_defs());
+        assert!(introspect_table(&connection, "missing")?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn schema_diff_against_live_table() -> SealionResult<()> {
+        let connection = Connection::open_in_memory()?;
+        create_table::<Product>(&connection)?;
+        assert_eq!(schema_matches::<Product>(&connection)?, vec![]);
+
+        connection.execute_batch("
+            CREATE TABLE events (id INTEGER PRIMARY KEY, name BLOB NOT NULL, location TEXT);
+        ")?;
+        assert_eq!(schema_matches::<Event>(&connection)?, vec![
+            SchemaDiff::TypeMismatch { column: "name".to_string(), expected: "TEXT".to_string(), actual: "BLOB".to_string() },
+            SchemaDiff::MissingColumn("created_at".to_string()),
+            SchemaDiff::ExtraColumn("location".to_string())
+        ]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn create_partial_unique_index() -> SealionResult<()> {
+        let connection = Connection::open_in_memory()?;
+        create_table::<Product>(&connection)?;
+
+        let mut index = IndexBuilder::for_table::<Product, _>("products_price", &["price", "quantity"])?;
+        index.unique().if_not_exists().r#where("quantity > 0");
+        assert_eq!(
+            index.build_sql_string(),
+            r#"CREATE UNIQUE INDEX IF NOT EXISTS "products_price" ON "products" ("price", "quantity") WHERE quantity > 0"#
+        );
+        index.execute(&connection)?;
+        index.execute(&connection)?;
+
+        let result = IndexBuilder::for_table::<Product, _>("products_colour", &["colour"]);
+        assert!(matches!(result, Err(SealionError::UnknownColumns(columns)) if columns == vec!["colour".to_string()]));
+
+        Ok(())
+    }
+
+    #[test]
+    fn create_audit_trigger() -> SealionResult<()> {
+        let connection = Connection::open_in_memory()?;
+        create_table::<Product>(&connection)?;
+        connection.execute("CREATE TABLE price_changes (product_id INTEGER, old_price REAL, new_price REAL)", [])?;
+
+        let mut trigger = TriggerBuilder::new("products_audit", "products", TriggerTiming::After, TriggerEvent::Update);
+        trigger
+            .when("OLD.price <> NEW.price")
+            .body("INSERT INTO price_changes VALUES (NEW.id, OLD.price, NEW.price)");
+        assert_eq!(
+            trigger.build_sql_string(),
+            r#"CREATE TRIGGER "products_audit" AFTER UPDATE ON "products" FOR EACH ROW WHEN OLD.price <> NEW.price BEGIN INSERT INTO price_changes VALUES (NEW.id, OLD.price, NEW.price); END"#
+        );
+        trigger.execute(&connection)?;
+
+        connection.execute_batch("
+            INSERT INTO products (id, price, quantity) VALUES (1, 2.5, 4);
+            UPDATE products SET quantity = 5;
+            UPDATE products SET price = 3.0;
+        ")?;
+        let changes: (i64, f64, f64) = connection.query_row("SELECT * FROM price_changes", [], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+        assert_eq!(changes, (1, 2.5, 3.0));
+        assert_eq!(connection.query_row("SELECT COUNT(*) FROM price_changes", [], |row| row.get::<_, i64>(0))?, 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn default_column_defs_are_untyped() {
+        struct Untyped;
+
+        impl Row for Untyped {
+            fn columns<'a>() -> &'a[&'a str] {
+                &["a", "b"]
+            }
+
+            fn parse_row(_: &rusqlite::Row) -> rusqlite::Result<Self> {
+                Ok(Self)
+            }
+        }
+
+        impl Table for Untyped {
+            type Key = i64;
+
+            fn table_name<'a>() -> &'a str {
+                "untyped"
+            }
+        }
+
+        assert_eq!(create_table_sql::<Untyped>(), r#"CREATE TABLE "untyped" ("a", "b")"#);
+    }
+}
@@ -0,0 +1,282 @@
+use std::collections::HashMap;
+use std::ops::Deref;
+use std::time::Duration;
+use rusqlite::{Connection, ToSql, params_from_iter};
+
+use crate::cache::prepare_cached;
+use crate::condition::number_placeholders;
+use crate::{bind_named_params, check_columns, retry_busy, to_value, write_where_clause, Condition, Row, RowParser, RowValues, SealionError, SealionResult, Table};
+
+/// The first SQLite version to support `RETURNING` clauses, encoded like `rusqlite::version_number()`.
+const RETURNING_MIN_VERSION: i32 = 3_035_000;
+
+pub struct DeleteQuery {
+    pub table_name: String,
+    pub where_clause: Option<String>,
+    pub conditions: Vec<Condition>,
+    pub numbered_placeholders: bool
+}
+
+impl DeleteQuery {
+    pub fn new<S: ToString>(table_name: S) -> Self {
+        Self {
+            table_name: table_name.to_string(),
+            where_clause: None,
+            conditions: Vec::new(),
+            numbered_placeholders: false
+        }
+    }
+
+    /// Builds a delete removing the row with the same primary key as `value`.
+    ///
+    /// Fails with `SealionError::UnknownColumns` if a primary key column isn't one of the columns of `R`, rather
+    /// than deleting every row.
+    pub fn by_primary_key<R: Table + RowValues>(value: &R) -> SealionResult<Self> {
+        let params = value.to_params();
+        let mut query = Self::new(R::table_name());
+        let mut unknown_columns = Vec::new();
+
+        for &primary_key in R::primary_key() {
+            match R::columns().iter().position(|column| *column == primary_key) {
+                Some(index) => { query.and_where(Condition::eq(primary_key, to_value(params[index])?)); },
+                None => unknown_columns.push(primary_key.to_string())
+            }
+        }
+        if !unknown_columns.is_empty() {
+            return Err(SealionError::UnknownColumns(unknown_columns));
+        }
+
+        Ok(query)
+    }
+
+    pub fn r#where<S: ToString>(&mut self, where_clause: S) -> &mut Self {
+        self.where_clause = Some(where_clause.to_string());
+        self
+    }
+
+    /// Same as `r#where`, without needing the raw identifier.
+    pub fn filter<S: ToString>(&mut self, where_clause: S) -> &mut Self {
+        self.r#where(where_clause)
+    }
+
+    /// Adds a condition that deleted rows must match, in addition to any other conditions. Its parameters are
+    /// bound when the delete runs.
+    pub fn and_where(&mut self, condition: Condition) -> &mut Self {
+        self.conditions.push(condition);
+        self
+    }
+
+    /// Same as `and_where`.
+    pub fn where_condition(&mut self, condition: Condition) -> &mut Self {
+        self.and_where(condition)
+    }
+
+    /// Renders the placeholders of the conditions as numbered `?NNN` placeholders, so a value used by several
+    /// conditions is only bound once. See `SelectQuery::numbered_placeholders`.
+    pub fn numbered_placeholders(&mut self, enabled: bool) -> &mut Self {
+        self.numbered_placeholders = enabled;
+        self
+    }
+
+    pub fn build_sql_string(&self) -> String {
+        let mut sql_string = format!("DELETE FROM {} ", self.table_name);
+
+        let mut conditions: Vec<String> = self.where_clause.iter().cloned().collect();
+        if self.numbered_placeholders {
+            conditions.extend(number_placeholders(&self.conditions).0);
+        } else {
+            conditions.extend(self.conditions.iter().map(|condition| condition.sql.clone()));
+        }
+        write_where_clause(&mut sql_string, &conditions);
+
+        sql_string
+    }
+
+    /// Returns the parameters bound by the conditions, in the order they appear in the SQL.
+    fn params(&self) -> Vec<&dyn ToSql> {
+        if self.numbered_placeholders {
+            return number_placeholders(&self.conditions).1;
+        }
+        self.conditions
+            .iter()
+            .flat_map(|condition| condition.params.iter())
+            .map(|param| param.as_ref())
+            .collect()
+    }
+
+    /// Like `execute`, but retries up to `attempts` times while the database is busy. See `retry_busy`.
+    pub fn execute_with_retry(&self, connection: impl Deref<Target = Connection>, attempts: u32, backoff: Duration) -> SealionResult<usize> {
+        retry_busy(attempts, backoff, || self.execute(&*connection))
+    }
+
+    /// Runs the delete and returns the number of rows removed.
+    pub fn execute(&self, connection: impl Deref<Target = Connection>) -> SealionResult<usize> {
+        let mut statement = prepare_cached(&connection, &self.build_sql_string())?;
+        Ok(statement.execute(params_from_iter(self.params()))?)
+    }
+
+    /// Like `execute`, but binds the named parameters in the SQL, such as `:name`, to the values in `named`.
+    /// See `SelectQuery::execute_named`.
+    pub fn execute_named(&self, connection: impl Deref<Target = Connection>, named: &HashMap<String, Box<dyn ToSql>>) -> SealionResult<usize> {
+        let mut statement = prepare_cached(&connection, &self.build_sql_string())?;
+        bind_named_params(&mut statement, self.params(), named)?;
+        Ok(statement.raw_execute()?)
+    }
+
+    /// Runs the delete and parses the removed rows into `R` using a `RETURNING` clause, so the deleted
+    /// rows are captured in the same statement instead of selecting them beforehand.
+    ///
+    /// `RETURNING` requires SQLite 3.35.0 or newer. On older versions `SealionError::Unsupported` is
+    /// returned without deleting anything.
+    pub fn execute_returning<R: Row>(&self, connection: impl Deref<Target = Connection>) -> SealionResult<Vec<R>> {
+        if rusqlite::version_number() < RETURNING_MIN_VERSION {
+            return Err(SealionError::Unsupported(format!("RETURNING requires SQLite 3.35.0, found {}", rusqlite::version())));
+        }
+
+        let mut sql_string = self.build_sql_string();
+        sql_string.push_str(&format!("RETURNING {}", R::columns().join(", ")));
+
+        let mut statement = prepare_cached(&connection, &sql_string)?;
+        check_columns(&statement, R::columns());
+
+        let rows = statement.query_map(params_from_iter(self.params()), R::parse_row as RowParser<R>)?
+            .collect::<rusqlite::Result<Vec<R>>>()?;
+        Ok(rows)
+    }
+}
+
+/// Deletes every row of the table of `R` and returns the number of rows removed.
+///
+/// This runs `DELETE FROM t` without a WHERE clause, which SQLite optimizes into clearing the table
+/// in one go. If `reset_sequence` is true, the `AUTOINCREMENT` counter of the table is reset as well, so the
+/// next row inserted starts from 1 again.
+pub fn truncate<R: Table>(connection: impl Deref<Target = Connection>, reset_sequence: bool) -> SealionResult<usize> {
+    let rows_removed = DeleteQuery::new(R::table_name()).execute(&*connection)?;
+
+    if reset_sequence {
+        // sqlite_sequence only exists once a table with AUTOINCREMENT has been created
+        let has_sequence: bool = prepare_cached(&connection, "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE name = 'sqlite_sequence')")?
+            .query_row([], |row| row.get(0))?;
+        if has_sequence {
+            prepare_cached(&connection, "DELETE FROM sqlite_sequence WHERE name = ?")?.execute([R::table_name()])?;
+        }
+    }
+
+    Ok(rows_removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use rusqlite::ToSql;
+
+    use crate::{truncate, Condition, DeleteQuery, InsertQuery, SealionError, SealionResult, SelectQuery, UpdateQuery};
+    use crate::tests::{setup_test_db, TestRow};
+
+    #[test]
+    fn delete_returning_rows() -> SealionResult<()> {
+        let connection = setup_test_db()?;
+
+        let deleted: Vec<TestRow> = DeleteQuery::new("test_table")
+            .r#where("optional IS NOT NULL")
+            .execute_returning(&connection)?;
+        assert_eq!(deleted, vec![
+            TestRow { id: 0, name: "Orange".to_string(), optional: Some("Strawberry".to_string()) },
+            TestRow { id: 2, name: "Peach".to_string(), optional: Some("Raspberry".to_string()) }
+        ]);
+
+        let remaining: Vec<TestRow> = SelectQuery::new("test_table").execute(&connection)?;
+        assert_eq!(remaining, vec![
+            TestRow { id: 1, name: "Apple".to_string(), optional: None }
+        ]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn delete_by_condition() -> SealionResult<()> {
+        let connection = setup_test_db()?;
+
+        let mut query = DeleteQuery::new("test_table");
+        query.where_condition(Condition::eq("id", 1));
+        assert_eq!(query.build_sql_string(), r#"DELETE FROM test_table WHERE "id" = ? "#);
+        assert_eq!(query.execute(&connection)?, 1);
+
+        let deleted: Vec<TestRow> = DeleteQuery::new("test_table")
+            .r#where("id >= 0")
+            .and_where(Condition::eq("name", "Peach"))
+            .execute_returning(&connection)?;
+        assert_eq!(deleted.len(), 1);
+        assert_eq!(SelectQuery::new("test_table").count(&connection)?, 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn delete_with_numbered_placeholders() -> SealionResult<()> {
+        let connection = setup_test_db()?;
+
+        let mut query = DeleteQuery::new("test_table");
+        query
+            .numbered_placeholders(true)
+            .and_where(Condition::or(vec![Condition::eq("name", "Orange"), Condition::eq("optional", "Orange")]));
+        assert_eq!(query.build_sql_string(), r#"DELETE FROM test_table WHERE ("name" = ?1) OR ("optional" = ?1) "#);
+        assert_eq!(query.execute(&connection)?, 1);
+        assert_eq!(SelectQuery::new("test_table").count(&connection)?, 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn execute_with_named_params() -> SealionResult<()> {
+        let connection = setup_test_db()?;
+        let mut named: HashMap<String, Box<dyn ToSql>> = HashMap::new();
+        named.insert("min_id".to_string(), Box::new(1));
+        named.insert(":berry".to_string(), Box::new("Cherry"));
+
+        let mut select = SelectQuery::new("test_table");
+        select.r#where("id >= :min_id").and_where(Condition::eq("name", "Peach"));
+        let rows: Vec<TestRow> = select.execute_named(&connection, &named)?;
+        assert_eq!(rows.iter().map(|row| row.id).collect::<Vec<_>>(), vec![2]);
+
+        let mut update = UpdateQuery::new("test_table");
+        update.set_expr("optional", ":berry").r#where("id >= :min_id");
+        assert_eq!(update.execute_named(&connection, &named)?, 2);
+
+        let mut delete = DeleteQuery::new("test_table");
+        delete.r#where("optional = :berry AND id < :max_id");
+        assert!(matches!(delete.execute_named(&connection, &named), Err(SealionError::MissingParameters(names)) if names == vec![":max_id".to_string()]));
+
+        named.insert("max_id".to_string(), Box::new(2));
+        assert_eq!(delete.execute_named(&connection, &named)?, 1);
+        let rows: Vec<TestRow> = SelectQuery::new("test_table").execute(&connection)?;
+        assert_eq!(rows.iter().map(|row| row.id).collect::<Vec<_>>(), vec![0, 2]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn truncate_table() -> SealionResult<()> {
+        let connection = setup_test_db()?;
+        assert_eq!(truncate::<TestRow>(&connection, true)?, 3);
+        assert_eq!(SelectQuery::new("test_table").count(&connection)?, 0);
+
+        connection.execute_batch("
+            DROP TABLE test_table;
+            CREATE TABLE test_table (id INTEGER PRIMARY KEY AUTOINCREMENT, name TEXT NOT NULL, optional TEXT);
+            INSERT INTO test_table (name) VALUES ('Orange'), ('Apple');
+        ")?;
+        let mut insert = InsertQuery::new("test_table");
+        insert.omit("id");
+
+        truncate::<TestRow>(&connection, false)?;
+        insert.execute(&connection, &TestRow { id: 0, name: "Peach".to_string(), optional: None })?;
+        assert_eq!(connection.last_insert_rowid(), 3);
+
+        truncate::<TestRow>(&connection, true)?;
+        insert.execute(&connection, &TestRow { id: 0, name: "Peach".to_string(), optional: None })?;
+        assert_eq!(connection.last_insert_rowid(), 1);
+
+        Ok(())
+    }
+}
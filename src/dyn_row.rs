@@ -0,0 +1,60 @@
+use std::collections::HashMap;
+use rusqlite::types::Value;
+
+use crate::Row;
+
+/// A row whose columns are only known at runtime.
+///
+/// Selecting `DynRow`s selects every column of the table (`SELECT *`), keyed by column name. This is
+/// meant for tooling and ad-hoc exploration where the schema isn't known at compile time.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DynRow {
+    pub values: HashMap<String, Value>
+}
+
+impl DynRow {
+    /// Returns the value of `column`, or `None` if the row has no such column.
+    pub fn get(&self, column: &str) -> Option<&Value> {
+        self.values.get(column)
+    }
+}
+
+impl Row for DynRow {
+    fn columns<'a>() -> &'a[&'a str] {
+        &["*"]
+    }
+
+    fn parse_row(row: &rusqlite::Row) -> rusqlite::Result<Self> {
+        let statement = row.as_ref();
+        let mut values = HashMap::with_capacity(statement.column_count());
+
+        for index in 0..statement.column_count() {
+            values.insert(statement.column_name(index)?.to_string(), row.get(index)?);
+        }
+
+        Ok(Self { values })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rusqlite::types::Value;
+
+    use crate::{DynRow, SealionResult, SelectQuery};
+    use crate::tests::setup_test_db;
+
+    #[test]
+    fn select_dyn_rows() -> SealionResult<()> {
+        let connection = setup_test_db()?;
+
+        let rows: Vec<DynRow> = SelectQuery::new("test_table").r#where("id = 1").execute(&connection)?;
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].values.len(), 3);
+        assert_eq!(rows[0].get("id"), Some(&Value::Integer(1)));
+        assert_eq!(rows[0].get("name"), Some(&Value::Text("Apple".to_string())));
+        assert_eq!(rows[0].get("optional"), Some(&Value::Null));
+        assert_eq!(rows[0].get("missing"), None);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,279 @@
+use rusqlite::types::Value;
+
+use crate::{Condition, Operator, SealionError, SealionResult, SelectQuery};
+
+/// Parses a filter written in a small search syntax into a `Condition`.
+///
+/// Every literal is bound as a parameter and every column name is quoted, so filters can safely come from
+/// untrusted input. The grammar is:
+///
+/// ```text
+/// filter     := and_filter ("OR" and_filter)*
+/// and_filter := term ("AND" term)*
+/// term       := "NOT" term | "(" filter ")" | column operator literal
+/// column     := [A-Za-z_][A-Za-z0-9_]*
+/// operator   := ":" | "=" | "!=" | "<>" | "<" | "<=" | ">" | ">="
+/// literal    := quoted string | integer | real | word
+/// ```
+///
+/// `:` is a synonym for `=`. Keywords are case-insensitive. Strings are quoted with `"` or `'`, and a
+/// backslash escapes the next character. An unquoted word that isn't a number is a string, so
+/// `name:Apple AND id>1` is a valid filter.
+///
+/// Fails with `SealionError::InvalidFilter` if `input` doesn't follow the grammar, or if it nests `NOT` and
+/// parentheses more than `MAX_FILTER_DEPTH` levels deep.
+///
+/// ```
+/// # use sealion::parse_filter;
+/// let condition = parse_filter("name:Apple OR (id >= 2 AND NOT optional = 'Raspberry')")?;
+/// assert_eq!(condition.sql, r#"("name" = ?) OR (("id" >= ?) AND (NOT ("optional" = ?)))"#);
+/// # Ok::<(), sealion::SealionError>(())
+/// ```
+pub fn parse_filter(input: &str) -> SealionResult<Condition> {
+    let mut parser = FilterParser { input, position: 0, depth: 0 };
+    let condition = parser.filter()?;
+
+    parser.skip_whitespace();
+    if parser.position < input.len() {
+        return Err(parser.error("Expected AND, OR or the end of the filter"));
+    }
+    Ok(condition)
+}
+
+impl SelectQuery {
+    /// Only selects rows matching the filter `input`. See `parse_filter` for the syntax.
+    pub fn where_filter(&mut self, input: &str) -> SealionResult<&mut Self> {
+        Ok(self.and_where(parse_filter(input)?))
+    }
+}
+
+/// How deeply `parse_filter` lets `NOT` and parentheses nest, so that hostile input can't overflow the stack.
+pub const MAX_FILTER_DEPTH: usize = 64;
+
+struct FilterParser<'a> {
+    input: &'a str,
+    /// The byte offset of the next character to parse.
+    position: usize,
+    /// How many `NOT`s and parentheses enclose the next character.
+    depth: usize
+}
+
+impl<'a> FilterParser<'a> {
+    fn filter(&mut self) -> SealionResult<Condition> {
+        let mut conditions = vec![self.and_filter()?];
+        while self.keyword("OR") {
+            conditions.push(self.and_filter()?);
+        }
+        Ok(Self::combine(conditions, Condition::or))
+    }
+
+    fn and_filter(&mut self) -> SealionResult<Condition> {
+        let mut conditions = vec![self.term()?];
+        while self.keyword("AND") {
+            conditions.push(self.term()?);
+        }
+        Ok(Self::combine(conditions, Condition::and))
+    }
+
+    fn combine(mut conditions: Vec<Condition>, join: fn(Vec<Condition>) -> Condition) -> Condition {
+        if conditions.len() == 1 {
+            conditions.remove(0)
+        } else {
+            join(conditions)
+        }
+    }
+
+    fn term(&mut self) -> SealionResult<Condition> {
+        if self.keyword("NOT") {
+            self.enter()?;
+            let condition = Condition::not(self.term()?);
+            self.depth -= 1;
+            return Ok(condition);
+        }
+
+        self.skip_whitespace();
+        if self.rest().starts_with('(') {
+            self.enter()?;
+            self.position += 1;
+            let condition = self.filter()?;
+            self.skip_whitespace();
+            if !self.rest().starts_with(')') {
+                return Err(self.error("Expected )"));
+            }
+            self.position += 1;
+            self.depth -= 1;
+            return Ok(condition);
+        }
+
+        let column = self.column()?;
+        let operator = self.operator()?;
+        let value = self.literal()?;
+        Ok(Condition::compare(column, operator, value))
+    }
+
+    fn column(&mut self) -> SealionResult<&'a str> {
+        self.skip_whitespace();
+        let rest = self.rest();
+        let length = rest
+            .char_indices()
+            .find(|&(index, c)| !(c == '_' || c.is_ascii_alphabetic() || (index > 0 && c.is_ascii_digit())))
+            .map_or(rest.len(), |(index, _)| index);
+
+        if length == 0 {
+            return Err(self.error("Expected a column name"));
+        }
+        self.position += length;
+        Ok(&rest[..length])
+    }
+
+    fn operator(&mut self) -> SealionResult<Operator> {
+        self.skip_whitespace();
+        // Longer operators come first so that `<=` isn't read as `<`
+        let operators = [
+            ("<=", Operator::Le),
+            (">=", Operator::Ge),
+            ("!=", Operator::Ne),
+            ("<>", Operator::Ne),
+            ("==", Operator::Eq),
+            ("=", Operator::Eq),
+            (":", Operator::Eq),
+            ("<", Operator::Lt),
+            (">", Operator::Gt)
+        ];
+
+        for (symbol, operator) in operators {
+            if self.rest().starts_with(symbol) {
+                self.position += symbol.len();
+                return Ok(operator);
+            }
+        }
+        Err(self.error("Expected an operator"))
+    }
+
+    fn literal(&mut self) -> SealionResult<Value> {
+        self.skip_whitespace();
+        let rest = self.rest();
+
+        match rest.chars().next() {
+            Some(quote @ ('"' | '\'')) => {
+                let mut text = String::new();
+                let mut chars = rest.char_indices().skip(1);
+                while let Some((index, c)) = chars.next() {
+                    match c {
+                        '\\' => match chars.next() {
+                            Some((_, escaped)) => text.push(escaped),
+                            None => break
+                        },
+                        c if c == quote => {
+                            self.position += index + 1;
+                            return Ok(Value::Text(text));
+                        },
+                        c => text.push(c)
+                    }
+                }
+                Err(self.error("Unterminated string"))
+            },
+            Some(_) => {
+                let length = rest.find(|c: char| c.is_whitespace() || c == '(' || c == ')').unwrap_or(rest.len());
+                if length == 0 {
+                    return Err(self.error("Expected a value"));
+                }
+                self.position += length;
+
+                let word = &rest[..length];
+                if let Ok(integer) = word.parse::<i64>() {
+                    Ok(Value::Integer(integer))
+                } else if let Ok(real) = word.parse::<f64>() {
+                    Ok(Value::Real(real))
+                } else {
+                    Ok(Value::Text(word.to_string()))
+                }
+            },
+            None => Err(self.error("Expected a value"))
+        }
+    }
+
+    /// Descends into a `NOT` or parentheses, failing if that nests them too deeply.
+    fn enter(&mut self) -> SealionResult<()> {
+        if self.depth == MAX_FILTER_DEPTH {
+            return Err(self.error("Filter is nested too deeply"));
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    /// Consumes `keyword` if it's the next word, ignoring case.
+    fn keyword(&mut self, keyword: &str) -> bool {
+        self.skip_whitespace();
+        let rest = self.rest();
+        let matches = rest.get(..keyword.len()).is_some_and(|word| word.eq_ignore_ascii_case(keyword))
+            && !rest[keyword.len()..].starts_with(|c: char| c == '_' || c.is_ascii_alphanumeric());
+
+        if matches {
+            self.position += keyword.len();
+        }
+        matches
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.position += rest.len() - rest.trim_start().len();
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.input[self.position..]
+    }
+
+    fn error(&self, message: &str) -> SealionError {
+        SealionError::InvalidFilter(format!("{} at position {}", message, self.position))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{parse_filter, SealionError, SealionResult, SelectQuery};
+    use super::MAX_FILTER_DEPTH;
+    use crate::tests::{setup_test_db, TestRow};
+
+    #[test]
+    fn filter_binds_literals() -> SealionResult<()> {
+        let connection = setup_test_db()?;
+
+        let rows: Vec<TestRow> = SelectQuery::new("test_table").where_filter("name:Apple OR id>1")?.execute(&connection)?;
+        assert_eq!(rows.iter().map(|row| row.id).collect::<Vec<_>>(), vec![1, 2]);
+
+        let rows: Vec<TestRow> = SelectQuery::new("test_table")
+            .where_filter(r#"not (optional = "Rasp\"berry" or name = 'Apple') and id <= 2.5"#)?
+            .execute(&connection)?;
+        assert_eq!(rows.iter().map(|row| row.id).collect::<Vec<_>>(), vec![0, 2]);
+
+        let condition = parse_filter(r#"name = "x' OR 1=1 --""#)?;
+        assert_eq!(condition.sql, r#""name" = ?"#);
+        assert_eq!(condition.params.len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_filters_are_rejected() {
+        for filter in ["", "é", "name", "name ~ Apple", "name = 'Apple", "(id = 1", "id = 1 id = 2", "1 = 1"] {
+            assert!(
+                matches!(parse_filter(filter), Err(SealionError::InvalidFilter(_))),
+                "{} should be rejected",
+                filter
+            );
+        }
+    }
+
+    #[test]
+    fn deeply_nested_filters_are_rejected() -> SealionResult<()> {
+        for filter in [format!("{}id = 1{}", "(".repeat(500_000), ")".repeat(500_000)), format!("{}id = 1", "NOT ".repeat(500_000))] {
+            assert!(matches!(parse_filter(&filter), Err(SealionError::InvalidFilter(_))));
+        }
+
+        let filter = format!("{}id = 1{}", "(".repeat(MAX_FILTER_DEPTH), ")".repeat(MAX_FILTER_DEPTH));
+        assert_eq!(parse_filter(&filter)?.sql, r#""id" = ?"#);
+
+        Ok(())
+    }
+}
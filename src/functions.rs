@@ -0,0 +1,80 @@
+use std::ops::Deref;
+use std::panic::UnwindSafe;
+use rusqlite::{Connection, ToSql};
+use rusqlite::functions::{Context, FunctionFlags};
+
+use crate::{SealionError, SealionResult};
+
+/// Registers the scalar function `name` taking `arg_count` arguments on the connection, so queries can
+/// call it like a built-in function. An `arg_count` of -1 accepts any number of arguments.
+///
+/// `f` reads its arguments from the `Context` with `Context::get`, and may fail with any `SealionError`.
+/// The query calling the function then fails with an error whose message is the `Display` output of the
+/// error. Set `deterministic` if the function always returns the same result for the same arguments, which
+/// lets SQLite use it in indexes and generated columns and evaluate it fewer times.
+///
+/// The function is only registered on this connection, until it's closed or the function is removed with
+/// `remove_function`, so it has to be registered again on every connection that uses it. It runs on
+/// whichever thread the query calling it runs on, which is why `f` has to be `Send`. A panic in `f` is
+/// caught and reported as an error instead of unwinding through SQLite.
+///
+/// ```
+/// # use sealion::{register_function, SelectQuery};
+/// let connection = rusqlite::Connection::open_in_memory()?;
+/// connection.execute_batch("CREATE TABLE fruit (name TEXT); INSERT INTO fruit VALUES ('Apple'), ('Kiwi');")?;
+///
+/// register_function(&connection, "reverse", 1, true, |context| {
+///     Ok(context.get::<String>(0)?.chars().rev().collect::<String>())
+/// })?;
+///
+/// assert_eq!(SelectQuery::new("fruit").filter("reverse(name) = 'iwiK'").count(&connection)?, 1);
+/// # Ok::<(), sealion::SealionError>(())
+/// ```
+pub fn register_function<F, T>(connection: impl Deref<Target = Connection>, name: &str, arg_count: i32, deterministic: bool, mut f: F) -> SealionResult<()>
+where F: FnMut(&Context<'_>) -> SealionResult<T> + Send + UnwindSafe + 'static,
+      T: ToSql {
+    let mut flags = FunctionFlags::SQLITE_UTF8;
+    if deterministic {
+        flags |= FunctionFlags::SQLITE_DETERMINISTIC;
+    }
+
+    connection.create_scalar_function(name, arg_count, flags, move |context| f(context).map_err(|err| match err {
+        SealionError::RusqliteError(err) => err,
+        err => rusqlite::Error::UserFunctionError(Box::new(err))
+    }))?;
+    Ok(())
+}
+
+/// Removes the function `name` taking `arg_count` arguments added with `register_function`.
+pub fn remove_function(connection: impl Deref<Target = Connection>, name: &str, arg_count: i32) -> SealionResult<()> {
+    connection.remove_function(name, arg_count)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{register_function, remove_function, SealionError, SealionResult, SelectQuery};
+    use crate::tests::{setup_test_db, TestRow};
+
+    #[test]
+    fn call_registered_function() -> SealionResult<()> {
+        let connection = setup_test_db()?;
+        register_function(&connection, "fruit_length", 1, true, |context| {
+            match context.get::<Option<String>>(0)? {
+                Some(text) => Ok(text.chars().count() as i64),
+                None => Err(SealionError::InvalidQuery("fruit_length of NULL".to_string()))
+            }
+        })?;
+
+        let rows: Vec<TestRow> = SelectQuery::new("test_table").filter("fruit_length(name) = 5").execute(&connection)?;
+        assert_eq!(rows.iter().map(|row| row.id).collect::<Vec<_>>(), vec![1, 2]);
+
+        let err = SelectQuery::new("test_table").filter("fruit_length(optional) > 0").execute::<TestRow>(&connection).unwrap_err();
+        assert!(err.to_string().contains("fruit_length of NULL"), "{}", err);
+
+        remove_function(&connection, "fruit_length", 1)?;
+        assert!(SelectQuery::new("test_table").filter("fruit_length(name) = 5").execute::<TestRow>(&connection).is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,123 @@
+use std::sync::RwLock;
+
+use crate::{Condition, SelectQuery};
+
+type SelectHook = Box<dyn Fn(&SelectQuery) -> Vec<Condition> + Send + Sync>;
+
+static SELECT_HOOKS: RwLock<Vec<SelectHook>> = RwLock::new(Vec::new());
+
+/// Registers a hook that adds conditions to every select query sealion runs, across all connections.
+///
+/// The hook is called with the query each time its SQL is built, and returns the conditions to add to it,
+/// which are ANDed with the query's own conditions and have their parameters bound along with them. This
+/// allows rules like `tenant_id = ?` to be enforced in one place rather than at every call site. The hook
+/// can't change the rest of the query, and should check `SelectQuery::table_name` to only restrict the
+/// tables the rule applies to.
+///
+/// Hooks apply to every way of running a `SelectQuery`, including counts and subqueries. The statements
+/// returned by `SelectQuery::prepare_statement` include the added conditions too, and are returned along with
+/// the parameters to bind to them. A hook must not register or clear hooks, which would deadlock.
+///
+/// ```
+/// # use sealion::{add_select_hook, Condition};
+/// add_select_hook(|query| match query.table_name.as_str() {
+///     "invoices" => vec![Condition::eq("tenant_id", 42)],
+///     _ => Vec::new()
+/// });
+/// ```
+pub fn add_select_hook<F: Fn(&SelectQuery) -> Vec<Condition> + Send + Sync + 'static>(hook: F) {
+    SELECT_HOOKS.write().unwrap_or_else(|err| err.into_inner()).push(Box::new(hook));
+}
+
+/// Removes every hook registered with `add_select_hook`.
+pub fn clear_select_hooks() {
+    SELECT_HOOKS.write().unwrap_or_else(|err| err.into_inner()).clear();
+}
+
+impl SelectQuery {
+    /// Returns the conditions the select hooks add to this query.
+    ///
+    /// This is called once each time the query runs, so that the SQL and the parameters bound to it are
+    /// built from the same conditions.
+    pub(crate) fn hook_conditions(&self) -> Vec<Condition> {
+        SELECT_HOOKS
+            .read()
+            .unwrap_or_else(|err| err.into_inner())
+            .iter()
+            .flat_map(|hook| hook(self))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rusqlite::Connection;
+
+    use rusqlite::params_from_iter;
+
+    use crate::{add_select_hook, Condition, SealionResult, SelectQuery};
+    use crate::tests::NamedRow;
+
+    #[test]
+    fn hooks_restrict_selects() -> SealionResult<()> {
+        let connection = Connection::open_in_memory()?;
+        connection.execute_batch("
+            CREATE TABLE hooked_tenants (id INTEGER PRIMARY KEY, name TEXT NOT NULL, tenant_id INTEGER NOT NULL);
+            INSERT INTO hooked_tenants VALUES (1, 'Apple', 1), (2, 'Peach', 2), (3, 'Plum', 1);
+        ")?;
+
+        // Hooks are global, so only the table used by this test is restricted
+        add_select_hook(|query| match query.table_name.as_str() {
+            "hooked_tenants" => vec![Condition::eq("tenant_id", 1)],
+            _ => Vec::new()
+        });
+
+        let mut query = SelectQuery::new("hooked_tenants");
+        query.where_eq("name", "Peach");
+        assert_eq!(
+            query.build_sql_string(&["id", "name"]),
+            r#"SELECT id, name FROM hooked_tenants WHERE ("name" = ?) AND ("tenant_id" = ?) "#
+        );
+        assert!(query.execute::<NamedRow>(&connection)?.is_empty());
+
+        let rows: Vec<NamedRow> = SelectQuery::new("hooked_tenants").execute(&connection)?;
+        assert_eq!(rows.iter().map(|row| row.id).collect::<Vec<_>>(), vec![1, 3]);
+        assert_eq!(SelectQuery::new("hooked_tenants").count(&connection)?, 2);
+
+        let mut subquery = SelectQuery::new("hooked_tenants");
+        subquery.select_exprs(&["id"]);
+        connection.execute_batch("CREATE TABLE hooked_names (id INTEGER, name TEXT); INSERT INTO hooked_names VALUES (2, 'Peach'), (3, 'Plum');")?;
+        let rows: Vec<NamedRow> = SelectQuery::new("hooked_names").where_in_subquery("id", subquery)?.execute(&connection)?;
+        assert_eq!(rows, vec![NamedRow { id: 3, name: "Plum".to_string() }]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn hooks_restrict_prepared_statements() -> SealionResult<()> {
+        let connection = Connection::open_in_memory()?;
+        connection.execute_batch("
+            CREATE TABLE hooked_prepared (id INTEGER PRIMARY KEY, name TEXT NOT NULL, tenant_id INTEGER NOT NULL);
+            INSERT INTO hooked_prepared VALUES (1, 'Apple', 1), (2, 'Peach', 2);
+        ")?;
+
+        add_select_hook(|query| match query.table_name.as_str() {
+            "hooked_prepared" => vec![Condition::eq("tenant_id", 1)],
+            _ => Vec::new()
+        });
+
+        let mut query = SelectQuery::new("hooked_prepared");
+        query.where_eq("name", "Peach");
+        let (mut statement, params) = query.prepare_statement::<NamedRow>(&connection)?;
+        assert_eq!(params.len(), 2);
+        let rows = statement.query_map(params_from_iter(params), |row| row.get::<_, i64>(0))?.collect::<rusqlite::Result<Vec<i64>>>()?;
+        assert!(rows.is_empty());
+
+        query.conditions = vec![Condition::eq("name", "Apple")];
+        let (mut statement, params) = query.prepare_statement::<NamedRow>(&connection)?;
+        let rows = statement.query_map(params_from_iter(params), |row| row.get::<_, i64>(0))?.collect::<rusqlite::Result<Vec<i64>>>()?;
+        assert_eq!(rows, vec![1]);
+
+        Ok(())
+    }
+}
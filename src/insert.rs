@@ -0,0 +1,455 @@
+use std::marker::PhantomData;
+use std::ops::Deref;
+use std::time::Duration;
+use rusqlite::{CachedStatement, Connection, ToSql, params_from_iter};
+use rusqlite::types::Value;
+
+use crate::cache::prepare_cached;
+use crate::{introspect_table, quote_ident, retry_busy, to_value, RowValues, SealionError, SealionResult, Table};
+
+/// What SQLite does when an insert violates a constraint. See `InsertQuery::or`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConflictResolution {
+    /// Deletes the rows that conflict with the new row before inserting it.
+    Replace,
+    /// Skips the new row, leaving the existing one in place.
+    Ignore,
+    /// Fails and undoes the changes made by the statement. This is what SQLite does by default.
+    Abort,
+    /// Fails, keeping the changes the statement made before the conflict.
+    Fail,
+    /// Fails and rolls back the whole transaction.
+    Rollback
+}
+
+impl ConflictResolution {
+    pub fn as_sql(&self) -> &'static str {
+        match self {
+            ConflictResolution::Replace => "REPLACE",
+            ConflictResolution::Ignore => "IGNORE",
+            ConflictResolution::Abort => "ABORT",
+            ConflictResolution::Fail => "FAIL",
+            ConflictResolution::Rollback => "ROLLBACK"
+        }
+    }
+}
+
+/// What an upsert does with a row that violates the constraint of its conflict target. See `InsertQuery::on_conflict`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum UpsertAction {
+    /// Skips the new row, rendering as `DO NOTHING`.
+    Nothing,
+    /// Sets the given columns of the existing row to the values of the new row, rendering as
+    /// `DO UPDATE SET col = excluded.col`.
+    Update(Vec<String>)
+}
+
+/// An `ON CONFLICT (target) DO ...` clause of an upsert.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConflictClause {
+    /// The columns of the unique constraint this clause handles. Only the last clause may leave it empty, to
+    /// handle every other constraint.
+    pub target: Vec<String>,
+    pub action: UpsertAction
+}
+
+impl ConflictClause {
+    pub fn build_sql_string(&self) -> String {
+        let mut sql_string = String::from("ON CONFLICT ");
+        if !self.target.is_empty() {
+            let target: Vec<String> = self.target.iter().map(|column| quote_ident(column)).collect();
+            sql_string.push_str(&format!("({}) ", target.join(", ")));
+        }
+
+        match &self.action {
+            UpsertAction::Nothing => sql_string.push_str("DO NOTHING"),
+            UpsertAction::Update(columns) => {
+                let assignments: Vec<String> = columns
+                    .iter()
+                    .map(|column| format!("{} = excluded.{}", quote_ident(column), quote_ident(column)))
+                    .collect();
+                sql_string.push_str(&format!("DO UPDATE SET {}", assignments.join(", ")));
+            }
+        }
+        sql_string
+    }
+}
+
+pub struct InsertQuery {
+    pub table_name: String,
+    /// Columns of the row that are left out of the insert.
+    pub omitted_columns: Vec<String>,
+    pub conflict_resolution: Option<ConflictResolution>,
+    /// The upsert clauses, rendered in order after the values.
+    pub conflict_clauses: Vec<ConflictClause>,
+    /// Column and value pairs inserted by `execute_values`, in the order they were given.
+    pub values: Vec<(String, Value)>
+}
+
+impl InsertQuery {
+    pub fn new<S: ToString>(table_name: S) -> Self {
+        Self {
+            table_name: table_name.to_string(),
+            omitted_columns: Vec::new(),
+            conflict_resolution: None,
+            conflict_clauses: Vec::new(),
+            values: Vec::new()
+        }
+    }
+
+    /// Creates an insert into the table of `R`, leaving out its generated columns.
+    pub fn for_table<R: Table>() -> Self {
+        let mut query = Self::new(R::table_name());
+        for column_def in R::column_defs() {
+            if column_def.generated.is_some() {
+                query.omit(column_def.name);
+            }
+        }
+        query
+    }
+
+    /// Leaves `column` out of the insert, so SQLite fills it in instead.
+    pub fn omit<S: ToString>(&mut self, column: S) -> &mut Self {
+        self.omitted_columns.push(column.to_string());
+        self
+    }
+
+    /// Resolves constraint violations with `resolution`, rendering as `INSERT OR <resolution>`.
+    pub fn or(&mut self, resolution: ConflictResolution) -> &mut Self {
+        self.conflict_resolution = Some(resolution);
+        self
+    }
+
+    /// Turns the insert into an upsert that does `action` when a row violates the unique constraint on the
+    /// `target` columns.
+    ///
+    /// This can be called several times to handle each unique constraint of the table differently. The clauses
+    /// are tried in the order they were added, and an empty `target` handles any constraint, so it must come last.
+    /// The columns are checked when the insert is prepared, which fails with `SealionError::UnknownColumns` if
+    /// one isn't a column of the row, or was left out with `omit`.
+    pub fn on_conflict(&mut self, target: &[&str], action: UpsertAction) -> &mut Self {
+        self.conflict_clauses.push(ConflictClause {
+            target: target.iter().map(|column| column.to_string()).collect(),
+            action
+        });
+        self
+    }
+
+    /// Checks that the conflict clauses only refer to `columns`, and that only the last one has no target.
+    fn check_conflict_clauses(&self, columns: &[&str]) -> SealionResult<()> {
+        let untargeted = self.conflict_clauses.iter().rev().skip(1).any(|clause| clause.target.is_empty());
+        if untargeted {
+            return Err(SealionError::InvalidQuery("Only the last ON CONFLICT clause may have no target".to_string()));
+        }
+
+        let unknown: Vec<String> = self.conflict_clauses
+            .iter()
+            .flat_map(|clause| {
+                let updated = match &clause.action {
+                    UpsertAction::Nothing => &[][..],
+                    UpsertAction::Update(columns) => &columns[..]
+                };
+                clause.target.iter().chain(updated)
+            })
+            .filter(|column| !columns.iter().any(|known| known.eq_ignore_ascii_case(column)))
+            .cloned()
+            .collect();
+        if !unknown.is_empty() {
+            return Err(SealionError::UnknownColumns(unknown));
+        }
+        Ok(())
+    }
+
+    /// Adds column and value pairs to insert with `execute_values`, in any order.
+    ///
+    /// Fails with `SealionError::InvalidQuery` if a column is given more than once.
+    pub fn values(&mut self, values: &[(&str, &dyn ToSql)]) -> SealionResult<&mut Self> {
+        for (column, value) in values {
+            if self.values.iter().any(|(existing, _)| existing == column) {
+                return Err(SealionError::InvalidQuery(format!("Column {} is given more than once", column)));
+            }
+            self.values.push((column.to_string(), to_value(*value)?));
+        }
+        Ok(self)
+    }
+
+    pub fn build_sql_string(&self, columns: &[&str]) -> String {
+        let mut sql_string = String::from("INSERT ");
+        if let Some(resolution) = &self.conflict_resolution {
+            sql_string.push_str(&format!("OR {} ", resolution.as_sql()));
+        }
+
+        let columns: Vec<String> = columns.iter().map(|column| quote_ident(column)).collect();
+        let placeholders = vec!["?"; columns.len()];
+        sql_string.push_str(&format!("INTO {} ({}) VALUES ({})", self.table_name, columns.join(", "), placeholders.join(", ")));
+        for clause in &self.conflict_clauses {
+            sql_string.push(' ');
+            sql_string.push_str(&clause.build_sql_string());
+        }
+        sql_string
+    }
+
+    /// Inserts `value` and returns the number of rows inserted.
+    pub fn execute<R: RowValues>(&self, connection: impl Deref<Target = Connection>, value: &R) -> SealionResult<usize> {
+        self.prepare::<R>(&connection)?.push(value)
+    }
+
+    /// Inserts a row from the pairs added with `values` and returns the number of rows inserted.
+    ///
+    /// The columns are put in the order the table declares them, which is read with `introspect_table`.
+    /// Fails with `SealionError::UnknownColumns` if a column isn't in the table, and with
+    /// `SealionError::MissingColumns` if a NOT NULL column without a default is left out. If the table
+    /// can't be found, the columns are sorted by name and left for SQLite to check.
+    pub fn execute_values(&self, connection: impl Deref<Target = Connection>) -> SealionResult<usize> {
+        let column_defs = introspect_table(&*connection, &self.table_name)?;
+        let mut values: Vec<&(String, Value)> = self.values.iter().collect();
+
+        if column_defs.is_empty() {
+            values.sort_by(|(a, _), (b, _)| a.cmp(b));
+        } else {
+            let position = |column: &str| column_defs.iter().position(|column_def| column_def.name.eq_ignore_ascii_case(column));
+
+            let unknown: Vec<String> = values.iter().filter(|(column, _)| position(column).is_none()).map(|(column, _)| column.clone()).collect();
+            if !unknown.is_empty() {
+                return Err(SealionError::UnknownColumns(unknown));
+            }
+
+            // An INTEGER PRIMARY KEY is an alias for the rowid, which SQLite fills in when it's left out
+            let missing: Vec<String> = column_defs
+                .iter()
+                .filter(|column_def| !column_def.nullable && column_def.default.is_none())
+                .filter(|column_def| !(column_def.primary_key && column_def.sql_type.eq_ignore_ascii_case("INTEGER")))
+                .filter(|column_def| !values.iter().any(|(column, _)| column_def.name.eq_ignore_ascii_case(column)))
+                .map(|column_def| column_def.name.clone())
+                .collect();
+            if !missing.is_empty() {
+                return Err(SealionError::MissingColumns(missing));
+            }
+
+            values.sort_by_key(|(column, _)| position(column));
+            let table_columns: Vec<&str> = column_defs.iter().map(|column_def| column_def.name.as_str()).collect();
+            self.check_conflict_clauses(&table_columns)?;
+        }
+
+        let columns: Vec<&str> = values.iter().map(|(column, _)| column.as_str()).collect();
+        let mut statement = prepare_cached(&connection, &self.build_sql_string(&columns))?;
+        Ok(statement.execute(params_from_iter(values.iter().map(|(_, value)| value)))?)
+    }
+
+    /// Like `execute`, but retries up to `attempts` times while the database is busy. See `retry_busy`.
+    pub fn execute_with_retry<R: RowValues>(&self, connection: impl Deref<Target = Connection>, value: &R, attempts: u32, backoff: Duration) -> SealionResult<usize> {
+        retry_busy(attempts, backoff, || self.execute(&*connection, value))
+    }
+
+    /// Prepares the insert once so that many rows can be inserted with it.
+    ///
+    /// This avoids building the SQL and looking up the cached statement for every row, which adds up when
+    /// ingesting large amounts of data. For the best throughput, push the rows inside a transaction.
+    pub fn prepare<'conn, R: RowValues>(&self, connection: &'conn Connection) -> SealionResult<PreparedInsert<'conn, R>> {
+        let column_indices: Vec<usize> = R::columns()
+            .iter()
+            .enumerate()
+            .filter(|(_, column)| !self.omitted_columns.iter().any(|omitted| omitted == *column))
+            .map(|(index, _)| index)
+            .collect();
+        let columns: Vec<&str> = column_indices.iter().map(|&index| R::columns()[index]).collect();
+        self.check_conflict_clauses(&columns)?;
+
+        Ok(PreparedInsert {
+            statement: prepare_cached(connection, &self.build_sql_string(&columns))?,
+            column_indices,
+            row_type: PhantomData
+        })
+    }
+}
+
+/// An insert statement that has been prepared for a specific `Row` type. See `InsertQuery::prepare`.
+pub struct PreparedInsert<'conn, R> {
+    statement: CachedStatement<'conn>,
+    /// The indices of the inserted columns in `Row::columns()`.
+    column_indices: Vec<usize>,
+    row_type: PhantomData<fn(&R)>
+}
+
+impl<'conn, R: RowValues> PreparedInsert<'conn, R> {
+    /// Inserts `value` and returns the number of rows inserted.
+    pub fn push(&mut self, value: &R) -> SealionResult<usize> {
+        let params = value.to_params();
+        Ok(self.statement.execute(params_from_iter(self.column_indices.iter().map(|&index| params[index])))?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rusqlite::Connection;
+
+    use crate::{ConflictResolution, InsertQuery, Row, SealionError, SealionResult, SelectQuery, UpsertAction};
+    use crate::tests::{setup_test_db, TestRow};
+
+    #[test]
+    fn insert_row() -> SealionResult<()> {
+        let connection = setup_test_db()?;
+
+        let row = TestRow { id: 3, name: "Pear".to_string(), optional: None };
+        assert_eq!(InsertQuery::new("test_table").execute(&connection, &row)?, 1);
+
+        let rows: Vec<TestRow> = SelectQuery::new("test_table").r#where("id = 3").execute(&connection)?;
+        assert_eq!(rows, vec![row]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn insert_many_prepared() -> SealionResult<()> {
+        let mut connection = setup_test_db()?;
+        let transaction = connection.transaction()?;
+
+        {
+            let mut insert = InsertQuery::new("test_table").prepare::<TestRow>(&transaction)?;
+            for id in 3..103 {
+                insert.push(&TestRow { id, name: format!("Fruit {}", id), optional: None })?;
+            }
+        }
+        transaction.commit()?;
+
+        let rows: Vec<TestRow> = SelectQuery::new("test_table").execute(&connection)?;
+        assert_eq!(rows.len(), 103);
+        assert_eq!(rows[102], TestRow { id: 102, name: "Fruit 102".to_string(), optional: None });
+
+        Ok(())
+    }
+
+    #[test]
+    fn insert_row_with_unusual_column_names() -> SealionResult<()> {
+        #[derive(Row)]
+        struct Setting {
+            #[sealion(column = "setting-id")]
+            setting_id: i64,
+            #[sealion(column = "setting-value")]
+            setting_value: String
+        }
+
+        let connection = Connection::open_in_memory()?;
+        connection.execute_batch(r#"CREATE TABLE settings ("setting-id" INTEGER PRIMARY KEY, "setting-value" TEXT)"#)?;
+        assert_eq!(InsertQuery::new("settings").execute(&connection, &Setting { setting_id: 1, setting_value: "dark".to_string() })?, 1);
+
+        let value: String = connection.query_row(r#"SELECT "setting-value" FROM settings WHERE "setting-id" = 1"#, [], |row| row.get(0))?;
+        assert_eq!(value, "dark");
+
+        Ok(())
+    }
+
+    #[test]
+    fn insert_or_ignore_skips_duplicates() -> SealionResult<()> {
+        let connection = setup_test_db()?;
+
+        let row = TestRow { id: 1, name: "Pear".to_string(), optional: None };
+        let mut query = InsertQuery::new("test_table");
+        query.or(ConflictResolution::Ignore);
+        assert_eq!(query.build_sql_string(&["id"]), r#"INSERT OR IGNORE INTO test_table ("id") VALUES (?)"#);
+        assert_eq!(query.execute(&connection, &row)?, 0);
+
+        let rows: Vec<TestRow> = SelectQuery::new("test_table").r#where("id = 1").execute(&connection)?;
+        assert_eq!(rows[0].name, "Apple");
+
+        Ok(())
+    }
+
+    #[test]
+    fn insert_or_replace_overwrites() -> SealionResult<()> {
+        let connection = setup_test_db()?;
+
+        let row = TestRow { id: 1, name: "Pear".to_string(), optional: None };
+        assert_eq!(InsertQuery::new("test_table").or(ConflictResolution::Replace).execute(&connection, &row)?, 1);
+
+        let rows: Vec<TestRow> = SelectQuery::new("test_table").r#where("id = 1").execute(&connection)?;
+        assert_eq!(rows, vec![row]);
+        assert_eq!(SelectQuery::new("test_table").count(&connection)?, 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn insert_named_values() -> SealionResult<()> {
+        let connection = setup_test_db()?;
+
+        let mut query = InsertQuery::new("test_table");
+        query.values(&[("optional", &"Lime"), ("name", &"Pear"), ("id", &3)])?;
+        assert_eq!(query.execute_values(&connection)?, 1);
+
+        let rows: Vec<TestRow> = SelectQuery::new("test_table").r#where("id = 3").execute(&connection)?;
+        assert_eq!(rows, vec![TestRow { id: 3, name: "Pear".to_string(), optional: Some("Lime".to_string()) }]);
+
+        // The id is an alias for the rowid, so it can be left out
+        InsertQuery::new("test_table").values(&[("name", &"Plum")])?.execute_values(&connection)?;
+        assert_eq!(connection.last_insert_rowid(), 4);
+
+        assert!(matches!(
+            InsertQuery::new("test_table").values(&[("name", &"Pear"), ("name", &"Plum")]),
+            Err(SealionError::InvalidQuery(_))
+        ));
+        assert!(matches!(
+            InsertQuery::new("test_table").values(&[("optional", &"Lime")])?.execute_values(&connection),
+            Err(SealionError::MissingColumns(columns)) if columns == vec!["name".to_string()]
+        ));
+        assert!(matches!(
+            InsertQuery::new("test_table").values(&[("name", &"Pear"), ("colour", &"Green")])?.execute_values(&connection),
+            Err(SealionError::UnknownColumns(columns)) if columns == vec!["colour".to_string()]
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn upsert_with_several_conflict_targets() -> SealionResult<()> {
+        #[derive(Row, Debug, PartialEq)]
+        struct Account {
+            email: String,
+            username: String,
+            logins: i64
+        }
+
+        let connection = setup_test_db()?;
+        connection.execute_batch("
+            CREATE TABLE accounts (email TEXT UNIQUE, username TEXT UNIQUE, logins INTEGER);
+            INSERT INTO accounts VALUES ('alice@example.com', 'alice', 1);
+        ")?;
+
+        let mut upsert = InsertQuery::new("accounts");
+        upsert
+            .on_conflict(&["email"], UpsertAction::Update(vec!["logins".to_string()]))
+            .on_conflict(&["username"], UpsertAction::Nothing);
+        assert_eq!(
+            upsert.build_sql_string(Account::columns()),
+            r#"INSERT INTO accounts ("email", "username", "logins") VALUES (?, ?, ?) ON CONFLICT ("email") DO UPDATE SET "logins" = excluded."logins" ON CONFLICT ("username") DO NOTHING"#
+        );
+
+        let account = |email: &str, username: &str, logins| Account { email: email.to_string(), username: username.to_string(), logins };
+        assert_eq!(upsert.execute(&connection, &account("alice@example.com", "alice2", 5))?, 1);
+        assert_eq!(upsert.execute(&connection, &account("bob@example.com", "alice", 7))?, 0);
+        assert_eq!(upsert.execute(&connection, &account("bob@example.com", "bob", 2))?, 1);
+
+        let accounts: Vec<Account> = SelectQuery::new("accounts").execute(&connection)?;
+        assert_eq!(accounts, vec![account("alice@example.com", "alice", 5), account("bob@example.com", "bob", 2)]);
+
+        let mut upsert = InsertQuery::new("accounts");
+        upsert.on_conflict(&["phone"], UpsertAction::Nothing);
+        assert!(matches!(
+            upsert.execute(&connection, &account("carol@example.com", "carol", 1)),
+            Err(SealionError::UnknownColumns(columns)) if columns == vec!["phone".to_string()]
+        ));
+
+        let mut upsert = InsertQuery::new("accounts");
+        upsert.omit("logins").on_conflict(&["email"], UpsertAction::Update(vec!["logins".to_string()]));
+        assert!(matches!(
+            upsert.execute(&connection, &account("alice@example.com", "alice", 9)),
+            Err(SealionError::UnknownColumns(columns)) if columns == vec!["logins".to_string()]
+        ));
+
+        let mut upsert = InsertQuery::new("accounts");
+        upsert.on_conflict(&[], UpsertAction::Nothing).on_conflict(&["email"], UpsertAction::Nothing);
+        assert!(matches!(upsert.execute(&connection, &account("carol@example.com", "carol", 1)), Err(SealionError::InvalidQuery(_))));
+
+        Ok(())
+    }
+}
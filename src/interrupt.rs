@@ -0,0 +1,54 @@
+use std::ops::Deref;
+use rusqlite::{Connection, InterruptHandle};
+
+/// Returns a handle that interrupts whatever `connection` is running when `InterruptHandle::interrupt` is
+/// called, which can be done from any thread.
+///
+/// The interrupted statement fails with `SealionError::Interrupted`, and an interrupted transaction is
+/// rolled back. Interrupting a connection that isn't running anything does nothing, and the handle stops
+/// doing anything once the connection is closed.
+pub fn interrupt_handle(connection: impl Deref<Target = Connection>) -> InterruptHandle {
+    connection.get_interrupt_handle()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::thread;
+    use std::time::Duration;
+
+    use crate::{interrupt_handle, SealionError, SealionResult, SelectQuery};
+    use crate::tests::setup_test_db;
+
+    #[test]
+    fn interrupt_running_query() -> SealionResult<()> {
+        let connection = setup_test_db()?;
+        let handle = interrupt_handle(&connection);
+        let done = Arc::new(AtomicBool::new(false));
+
+        // Keep interrupting until the query fails, since an interrupt before it starts does nothing
+        let interrupter = {
+            let done = Arc::clone(&done);
+            thread::spawn(move || while !done.load(Ordering::SeqCst) {
+                handle.interrupt();
+                thread::sleep(Duration::from_millis(10));
+            })
+        };
+
+        let mut query = SelectQuery::expression();
+        query.select_exprs(&["(WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n) SELECT MAX(i) FROM n)"]);
+        let result = query.execute_discard(&connection);
+        done.store(true, Ordering::SeqCst);
+        interrupter.join().unwrap();
+
+        let err = result.unwrap_err();
+        assert!(matches!(err, SealionError::Interrupted(_)), "{:?}", err);
+        assert_eq!(err.user_message(), "The operation was cancelled");
+
+        // The connection can be used again afterwards
+        assert_eq!(SelectQuery::new("test_table").count(&connection)?, 3);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,94 @@
+use std::ops::Deref;
+use rusqlite::{Connection, params_from_iter};
+use rusqlite::types::ValueRef;
+use serde_json::{Map, Number, Value as JsonValue};
+
+use crate::{SealionResult, SelectQuery};
+
+impl SelectQuery {
+    /// Runs the query and returns each row as a JSON object keyed by column name.
+    ///
+    /// This selects `select_exprs`, or every column if there are none. NULL becomes `null`, integers and reals
+    /// become numbers, blobs become arrays of bytes and text becomes a string. The declared type of the column
+    /// is used to refine this: integers in `BOOLEAN` columns become booleans, and text in `JSON` columns is
+    /// embedded as parsed JSON when it's valid.
+    pub fn execute_json(&self, connection: impl Deref<Target = Connection>) -> SealionResult<Vec<JsonValue>> {
+        let hooked = self.hook_conditions();
+        let mut statement = self.prepare_statement_with(&connection, &self.selected_columns(&["*"]), &hooked)?;
+        let columns: Vec<(String, String)> = statement
+            .columns()
+            .iter()
+            .map(|column| (column.name().to_string(), column.decl_type().unwrap_or_default().to_ascii_uppercase()))
+            .collect();
+
+        let mut rows = statement.query(params_from_iter(self.params(&hooked)))?;
+        let mut objects = Vec::new();
+        while let Some(row) = rows.next()? {
+            let mut object = Map::with_capacity(columns.len());
+            for (index, (name, decl_type)) in columns.iter().enumerate() {
+                object.insert(name.clone(), to_json(row.get_ref(index)?, decl_type));
+            }
+            objects.push(JsonValue::Object(object));
+        }
+
+        Ok(objects)
+    }
+}
+
+fn to_json(value: ValueRef, decl_type: &str) -> JsonValue {
+    match value {
+        ValueRef::Null => JsonValue::Null,
+        ValueRef::Integer(i) if decl_type.contains("BOOL") => JsonValue::Bool(i != 0),
+        ValueRef::Integer(i) => JsonValue::Number(i.into()),
+        // NaN and infinities have no JSON representation
+        ValueRef::Real(f) => Number::from_f64(f).map_or(JsonValue::Null, JsonValue::Number),
+        ValueRef::Text(text) => {
+            let text = String::from_utf8_lossy(text);
+            if decl_type == "JSON" {
+                if let Ok(json) = serde_json::from_str(&text) {
+                    return json;
+                }
+            }
+            JsonValue::String(text.into_owned())
+        },
+        ValueRef::Blob(bytes) => JsonValue::Array(bytes.iter().map(|&byte| JsonValue::from(byte)).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rusqlite::Connection;
+    use serde_json::json;
+
+    use crate::{SealionResult, SelectQuery};
+    use crate::tests::setup_test_db;
+
+    #[test]
+    fn select_rows_as_json() -> SealionResult<()> {
+        let connection = setup_test_db()?;
+
+        let rows = SelectQuery::new("test_table").r#where("id < 2").execute_json(&connection)?;
+        assert_eq!(rows, vec![
+            json!({ "id": 0, "name": "Orange", "optional": "Strawberry" }),
+            json!({ "id": 1, "name": "Apple", "optional": null })
+        ]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn declared_types_refine_json() -> SealionResult<()> {
+        let connection = Connection::open_in_memory()?;
+        connection.execute_batch("
+            CREATE TABLE settings (enabled BOOLEAN, options JSON, ratio REAL, data BLOB);
+            INSERT INTO settings VALUES (1, '{\"depth\": 2}', 0.5, x'0102');
+        ")?;
+
+        let rows = SelectQuery::new("settings").execute_json(&connection)?;
+        assert_eq!(rows, vec![
+            json!({ "enabled": true, "options": { "depth": 2 }, "ratio": 0.5, "data": [1, 2] })
+        ]);
+
+        Ok(())
+    }
+}
@@ -1,19 +1,199 @@
+// Lets the code generated by sealion_macros refer to `::sealion` from within this crate.
+extern crate self as sealion;
+
 use std::result;
-use std::fmt::Write;
+use std::ops::Deref;
+use std::time::Duration;
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+use std::marker::PhantomData;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU8, Ordering};
 use thiserror::Error;
 use log::warn;
-use rusqlite::{Statement, Params, MappedRows, Connection, CachedStatement};
+use rusqlite::{Statement, Params, MappedRows, Connection, CachedStatement, OptionalExtension, ToSql, params_from_iter};
+use rusqlite::types::{FromSql, ToSqlOutput, Type, Value};
+
+use cache::prepare_cached;
+use condition::{json_extract, number_placeholders};
+
+mod attach;
+mod busy;
+mod cache;
+mod channel;
+mod condition;
+mod ddl;
+mod delete;
+mod dyn_row;
+mod filter;
+#[cfg(feature = "functions")]
+mod functions;
+mod hooks;
+mod insert;
+mod interrupt;
+#[cfg(feature = "serde_json")]
+mod json;
+mod maintenance;
+#[cfg(feature = "indexmap")]
+mod ordered;
+mod pager;
+mod pragma;
+mod registry;
+mod schema_version;
+#[cfg(feature = "serde")]
+mod serde_row;
+mod shared;
+mod snapshot;
+#[cfg(feature = "async")]
+mod stream;
+mod transaction;
+mod types;
+mod update;
+
+pub use attach::{attach, detach};
+pub use busy::{retry_busy, set_busy_handler, set_busy_timeout};
+pub use cache::{reset_statement_stats, set_statement_tracking, set_statement_warning_threshold, statement_stats, StatementStats, DEFAULT_STATEMENT_WARNING_THRESHOLD};
+pub use condition::{Condition, Operator};
+pub use ddl::{create_table, create_table_sql, introspect_table, schema_matches, ColumnDef, GeneratedStorage, IndexBuilder, SchemaDiff, TriggerBuilder, TriggerEvent, TriggerTiming};
+pub use delete::{truncate, DeleteQuery};
+pub use dyn_row::DynRow;
+pub use filter::{parse_filter, MAX_FILTER_DEPTH};
+#[cfg(feature = "functions")]
+pub use functions::{register_function, remove_function};
+pub use hooks::{add_select_hook, clear_select_hooks};
+pub use insert::{ConflictClause, ConflictResolution, InsertQuery, PreparedInsert, UpsertAction};
+#[cfg(feature = "indexmap")]
+pub use indexmap::IndexMap;
+pub use interrupt::interrupt_handle;
+pub use maintenance::{changes, integrity_check, total_changes, Integrity};
+pub use pager::{KeysetPager, KeysetRow};
+pub use pragma::{pragma, ColumnInfo, ForeignKeyInfo, IndexInfo};
+pub use registry::QueryRegistry;
+pub use schema_version::{check_schema_version, set_schema_version, SCHEMA_VERSIONS_TABLE};
+#[cfg(feature = "serde")]
+pub use serde_row::{DeserializeError, SerdeRow};
+pub use shared::SharedConnection;
+pub use snapshot::{dump_table, load_table};
+#[cfg(feature = "async")]
+pub use stream::RowStream;
+pub use transaction::{with_counted_transaction, with_deferred_foreign_keys, with_read_snapshot, with_transaction, with_transaction_behavior};
+pub use types::{get_f64_coerce, get_i64_coerce, SqlBool, UnixTimestamp, UnixTimestampMillis};
+pub use update::{Assignment, PreparedUpdate, UpdateQuery};
+pub use sealion_macros::Row;
+pub use rusqlite;
+pub use rusqlite::{InterruptHandle, TransactionBehavior};
 
 #[derive(Error, Debug)]
 pub enum SealionError {
     #[error(transparent)]
-    IoError(#[from] std::fmt::Error),
+    RusqliteError(rusqlite::Error),
+    /// The database was locked by another connection. The operation can be retried, see `retry_busy`.
+    #[error("Database is busy: {0}")]
+    Busy(#[source] rusqlite::Error),
+    /// The statement was interrupted with an `InterruptHandle`. See `interrupt_handle`.
+    #[error("Interrupted: {0}")]
+    Interrupted(#[source] rusqlite::Error),
+    #[error("Unsupported by this version of SQLite: {0}")]
+    Unsupported(String),
+    #[error("No query registered with the name {0}")]
+    UnknownQuery(String),
+    #[error("Statement does not select the required columns: {}", .0.join(", "))]
+    MissingColumns(Vec<String>),
+    #[error("Table does not have the columns: {}", .0.join(", "))]
+    UnknownColumns(Vec<String>),
+    /// The statement has named parameters without a value. See `SelectQuery::execute_named`.
+    #[error("No value for the parameters: {}", .0.join(", "))]
+    MissingParameters(Vec<String>),
+    #[error("Unknown operator {0}")]
+    UnknownOperator(String),
+    #[error("Invalid query: {0}")]
+    InvalidQuery(String),
+    #[error("Invalid filter: {0}")]
+    InvalidFilter(String),
     #[error(transparent)]
-    RusqliteError(#[from] rusqlite::Error)
+    ColumnParse(ColumnParseError),
+    /// The schema version stored for a table isn't the one its `Table` expects. See `check_schema_version`.
+    #[error("Table {table} is at schema version {}, expected version {expected}", .found.map_or_else(|| "none".to_string(), |version| version.to_string()))]
+    SchemaVersion {
+        table: String,
+        expected: u32,
+        found: Option<u32>
+    },
+    /// A row of the results couldn't be parsed. `row` is the zero-based index of the row in the results.
+    #[error("Failed to parse row {row}: {source}")]
+    ParseError {
+        row: usize,
+        #[source]
+        source: Box<SealionError>
+    }
+}
+
+impl From<rusqlite::Error> for SealionError {
+    fn from(err: rusqlite::Error) -> Self {
+        match err {
+            rusqlite::Error::SqliteFailure(rusqlite::ffi::Error { code: rusqlite::ErrorCode::DatabaseBusy, .. }, _) => SealionError::Busy(err),
+            rusqlite::Error::SqliteFailure(rusqlite::ffi::Error { code: rusqlite::ErrorCode::OperationInterrupted, .. }, _) => SealionError::Interrupted(err),
+            rusqlite::Error::FromSqlConversionFailure(index, data_type, source) => match source.downcast::<ColumnParseError>() {
+                Ok(column_err) => SealionError::ColumnParse(*column_err),
+                Err(source) => SealionError::RusqliteError(rusqlite::Error::FromSqlConversionFailure(index, data_type, source))
+            },
+            _ => SealionError::RusqliteError(err)
+        }
+    }
+}
+
+// Extended result codes for constraint violations, which the bundled bindings don't define.
+const SQLITE_CONSTRAINT_FOREIGNKEY: i32 = 787;
+const SQLITE_CONSTRAINT_NOTNULL: i32 = 1299;
+const SQLITE_CONSTRAINT_PRIMARYKEY: i32 = 1555;
+const SQLITE_CONSTRAINT_UNIQUE: i32 = 2067;
+
+impl SealionError {
+    /// Returns a message describing the error that is safe to show to end users.
+    ///
+    /// Unlike the `Display` output, this never includes SQL, table or column names, or values, so it can be
+    /// returned from an API without leaking how the data is stored. Errors caused by the application rather
+    /// than the user, such as malformed queries, are all reported as an internal error. Log the error itself
+    /// to keep the details.
+    pub fn user_message(&self) -> String {
+        use rusqlite::ErrorCode;
+
+        let message = match self {
+            SealionError::ParseError { source, .. } => return source.user_message(),
+            SealionError::RusqliteError(rusqlite::Error::QueryReturnedNoRows) => "The record was not found",
+            SealionError::RusqliteError(rusqlite::Error::SqliteFailure(err, _)) => match (err.code, err.extended_code) {
+                (ErrorCode::ConstraintViolation, SQLITE_CONSTRAINT_PRIMARYKEY | SQLITE_CONSTRAINT_UNIQUE) => "Duplicate entry",
+                (ErrorCode::ConstraintViolation, SQLITE_CONSTRAINT_FOREIGNKEY) => "The record refers to, or is referred to by, another record",
+                (ErrorCode::ConstraintViolation, SQLITE_CONSTRAINT_NOTNULL) => "A required value is missing",
+                (ErrorCode::ConstraintViolation, _) => "A value is not allowed",
+                (ErrorCode::DatabaseLocked, _) => "The database is busy, please try again",
+                (ErrorCode::DiskFull, _) => "The database is full",
+                (ErrorCode::ReadOnly, _) => "The database is read-only",
+                _ => "An internal error occurred"
+            },
+            SealionError::Busy(_) => "The database is busy, please try again",
+            SealionError::Interrupted(_) => "The operation was cancelled",
+            SealionError::Unsupported(_) => "This operation is not supported",
+            SealionError::UnknownOperator(_) | SealionError::InvalidFilter(_) => "The filter is invalid",
+            SealionError::RusqliteError(_)
+            | SealionError::UnknownQuery(_)
+            | SealionError::MissingColumns(_)
+            | SealionError::UnknownColumns(_)
+            | SealionError::MissingParameters(_)
+            | SealionError::InvalidQuery(_)
+            | SealionError::ColumnParse(_)
+            | SealionError::SchemaVersion { .. } => "An internal error occurred"
+        };
+        message.to_string()
+    }
 }
 
 type SealionResult<T> = result::Result<T, SealionError>;
 
+/// The function pointer type used to map rusqlite rows into `Row` implementations.
+pub type RowParser<R> = fn(&rusqlite::Row) -> rusqlite::Result<R>;
+
 pub trait Row: Sized {
     /// Returns a slice of the column names for this row.
     /// This method is primary used for building queries.
@@ -22,184 +202,2826 @@ pub trait Row: Sized {
     /// Parses an instance of `Self` from an rusqlite row.
     fn parse_row(row: &rusqlite::Row) -> rusqlite::Result<Self>;
 
+    /// Returns the index of the column called `name` in `row`.
+    ///
+    /// Looking values up by name is the recommended way to write `parse_row` by hand. Unlike positional
+    /// indices, it keeps working when the statement selects the columns in a different order.
+    ///
+    /// ```
+    /// # use sealion::Row;
+    /// struct Fruit {
+    ///     id: u64,
+    ///     name: String
+    /// }
+    ///
+    /// impl Row for Fruit {
+    ///     fn columns<'a>() -> &'a[&'a str] {
+    ///         &["id", "name"]
+    ///     }
+    ///
+    ///     fn parse_row(row: &rusqlite::Row) -> rusqlite::Result<Self> {
+    ///         Ok(Self {
+    ///             id: row.get(Self::column_index(row, "id")?)?,
+    ///             name: row.get(Self::column_index(row, "name")?)?
+    ///         })
+    ///     }
+    /// }
+    /// ```
+    fn column_index(row: &rusqlite::Row, name: &str) -> rusqlite::Result<usize> {
+        row.as_ref().column_index(name)
+    }
+
     /// Returns an iterator of `Self` from an rusqlite prepared statement.
     /// It is expected that the prepared statement is a select query of somekind.
-    fn from_statement<'stmt, P: Params>(statement: &'stmt mut Statement, params: P) -> SealionResult<MappedRows<'stmt, fn(&rusqlite::Row) -> rusqlite::Result<Self>>> {
-        check_columns(&statement, Self::columns());
-        statement.query_map(params, Self::parse_row as fn(&rusqlite::Row) -> rusqlite::Result<Self>)
-            .map_err(|err| SealionError::RusqliteError(err))
+    fn from_statement<'stmt, P: Params>(statement: &'stmt mut Statement, params: P) -> SealionResult<MappedRows<'stmt, RowParser<Self>>> {
+        check_columns(statement, Self::columns());
+        statement.query_map(params, Self::parse_row as RowParser<Self>)
+            .map_err(SealionError::from)
+    }
+
+    /// Like `from_statement`, but for rows that are parsed by name (see `column_index`) and so don't
+    /// care about the order of the selected columns.
+    ///
+    /// Instead of warning about mismatched column order, this checks that the statement selects every
+    /// column in `Self::columns()`, and fails with `SealionError::MissingColumns` listing any that
+    /// are absent.
+    fn from_statement_by_name<'stmt, P: Params>(statement: &'stmt mut Statement, params: P) -> SealionResult<MappedRows<'stmt, RowParser<Self>>> {
+        check_required_columns(statement, Self::columns())?;
+        statement.query_map(params, Self::parse_row as RowParser<Self>)
+            .map_err(SealionError::from)
+    }
+
+    /// Like `from_statement`, but parses each row with `f` instead of `Self::parse_row`.
+    ///
+    /// This allows a different parsing strategy for a single query, such as a partial parse. Since
+    /// `f` may read any columns it likes, the selected columns aren't checked against `Self::columns()`.
+    fn from_statement_with<'stmt, P, F>(statement: &'stmt mut Statement, params: P, f: F) -> SealionResult<MappedRows<'stmt, F>>
+    where P: Params,
+          F: FnMut(&rusqlite::Row) -> rusqlite::Result<Self> {
+        statement.query_map(params, f)
+            .map_err(SealionError::from)
+    }
+}
+
+/// A column of a row that couldn't be converted to the type it was read as. See `get_named`.
+#[derive(Error, Debug)]
+#[error("Failed to parse column {column} as {expected}: {source}")]
+pub struct ColumnParseError {
+    pub column: String,
+    /// The name of the Rust type the column was read as.
+    pub expected: &'static str,
+    #[source]
+    pub source: rusqlite::Error
+}
+
+/// Reads the column called `name` from `row`, naming the column and the expected type in the error if the
+/// value can't be converted. The error surfaces as `SealionError::ColumnParse` once converted. This is
+/// what `#[derive(Row)]` uses to read each field.
+pub fn get_named<T: FromSql>(row: &rusqlite::Row, name: &str) -> rusqlite::Result<T> {
+    let index = row.as_ref().column_index(name)?;
+    get_idx(row, index, name)
+}
+
+/// Like `get_named`, but reads the column at `index`, which is called `column_name`.
+pub fn get_idx<T: FromSql>(row: &rusqlite::Row, index: usize, column_name: &str) -> rusqlite::Result<T> {
+    row.get(index).map_err(|err| column_parse_error(row, index, column_name, std::any::type_name::<T>(), err))
+}
+
+/// Parses a row whose shape depends on a tag column, such as an event log storing different kinds of events.
+///
+/// The tag is read from `tag_column` and compared by value against each tag in `parsers`, and `row` is
+/// parsed with the function paired with the first one that matches. Fails with a `ColumnParseError` for `tag_column` if
+/// no tag matches. This is meant for implementing `Row::parse_row` for an enum:
+///
+/// ```
+/// # use sealion::{get_named, parse_tagged, Row};
+/// enum Event {
+///     Click { x: i64 },
+///     Key { key: String }
+/// }
+///
+/// impl Row for Event {
+///     fn columns<'a>() -> &'a [&'a str] {
+///         &["kind", "x", "key"]
+///     }
+///
+///     fn parse_row(row: &rusqlite::Row) -> rusqlite::Result<Self> {
+///         parse_tagged(row, "kind", &[
+///             ("click", |row| Ok(Event::Click { x: get_named(row, "x")? })),
+///             ("key", |row| Ok(Event::Key { key: get_named(row, "key")? }))
+///         ])
+///     }
+/// }
+/// ```
+pub fn parse_tagged<T: ToSql, R>(row: &rusqlite::Row, tag_column: &str, parsers: &[(T, RowParser<R>)]) -> rusqlite::Result<R> {
+    let index = row.as_ref().column_index(tag_column)?;
+    let tag: Value = row.get(index)?;
+
+    for (parser_tag, parser) in parsers {
+        if to_value(parser_tag)? == tag {
+            return parser(row);
+        }
+    }
+
+    let err = rusqlite::Error::FromSqlConversionFailure(index, tag.data_type(), format!("unknown tag {:?}", tag).into());
+    Err(column_parse_error(row, index, tag_column, std::any::type_name::<T>(), err))
+}
+
+/// Wraps `err`, which was raised reading the column at `index` as `expected`, in a `ColumnParseError`.
+pub(crate) fn column_parse_error(row: &rusqlite::Row, index: usize, column_name: &str, expected: &'static str, err: rusqlite::Error) -> rusqlite::Error {
+    let data_type = row.get_ref(index).map_or(Type::Null, |value| value.data_type());
+    rusqlite::Error::FromSqlConversionFailure(index, data_type, Box::new(ColumnParseError {
+        column: column_name.to_string(),
+        expected,
+        source: err
+    }))
+}
+
+/// A `Row` that can report the values of its columns, which is needed to write it back to the database.
+///
+/// This is the write-side counterpart of `Row::parse_row`, and is implemented by `#[derive(Row)]`.
+pub trait RowValues: Row {
+    /// Returns the value of each column as a parameter, in the same order as `Row::columns()`.
+    fn to_params(&self) -> Vec<&dyn ToSql>;
+
+    /// Returns the value of each column as a named parameter, in the same order as `Row::columns()`.
+    /// The name of each parameter is the column name prefixed with `:`, e.g. `:id`.
+    fn to_named_params(&self) -> Vec<(String, &dyn ToSql)> {
+        Self::columns()
+            .iter()
+            .map(|column| format!(":{}", column))
+            .zip(self.to_params())
+            .collect()
+    }
+
+    /// Returns the owned value of each column, in the same order as `Row::columns()`.
+    fn values(&self) -> rusqlite::Result<Vec<Value>> {
+        self.to_params().into_iter().map(to_value).collect()
+    }
+
+    /// Returns the columns that have a value, along with that value. NULL columns are left out.
+    ///
+    /// This is used for querying by example, so a row with its `Option` fields left as `None` matches
+    /// on the remaining fields only.
+    fn present_values(&self) -> rusqlite::Result<Vec<(&'static str, Value)>> {
+        Ok(Self::columns()
+            .iter()
+            .copied()
+            .zip(self.values()?)
+            .filter(|(_, value)| *value != Value::Null)
+            .collect())
+    }
+
+    /// Returns the values of this row keyed by column name.
+    fn value_map(&self) -> rusqlite::Result<HashMap<&'static str, Value>> {
+        Ok(Self::columns().iter().copied().zip(self.values()?).collect())
+    }
+}
+
+/// Converts a parameter into an owned `Value`.
+pub fn to_value(param: &dyn ToSql) -> rusqlite::Result<Value> {
+    match param.to_sql()? {
+        ToSqlOutput::Borrowed(value) => Ok(value.into()),
+        ToSqlOutput::Owned(value) => Ok(value),
+        _ => Err(rusqlite::Error::ToSqlConversionFailure("Parameter can't be converted to a value".into()))
+    }
+}
+
+/// Binds the named parameters of `statement`, such as `:name`, to the values in `named`, and its other
+/// parameters to `positional` in order. Names are looked up with and without their prefix, so `:name` can be
+/// given as either `name` or `:name`.
+///
+/// Fails with `SealionError::MissingParameters` listing the named parameters that aren't in `named`, and with
+/// `SealionError::InvalidQuery` if `positional` doesn't have a value for every other parameter.
+pub(crate) fn bind_named_params(statement: &mut Statement, positional: Vec<&dyn ToSql>, named: &HashMap<String, Box<dyn ToSql>>) -> SealionResult<()> {
+    let mut positional = positional.into_iter();
+    let mut missing = Vec::new();
+
+    for index in 1..=statement.parameter_count() {
+        let name = statement.parameter_name(index).filter(|name| name.starts_with([':', '@', '$'])).map(str::to_string);
+        match name {
+            Some(name) => match named.get(&name).or_else(|| named.get(&name[1..])) {
+                Some(value) => statement.raw_bind_parameter(index, value)?,
+                None => missing.push(name)
+            },
+            None => match positional.next() {
+                Some(value) => statement.raw_bind_parameter(index, value)?,
+                None => return Err(SealionError::InvalidQuery(format!("No value for parameter {}", index)))
+            }
+        }
+    }
+
+    if !missing.is_empty() {
+        return Err(SealionError::MissingParameters(missing));
     }
+    Ok(())
 }
 
-fn check_columns(statement: &Statement, columns: &[& str]) {
+pub(crate) fn check_columns(statement: &Statement, columns: &[& str]) {
+    // A wildcard selects whatever columns the table has, so there's nothing to check against.
+    if columns == ["*"] {
+        return;
+    }
+
     if statement.column_count() != columns.len() {
-        warn!(target: "sealion_parsing_events", 
+        report_column_mismatch(format!(
             "Column count mismatch. Expected {} columns, statement only selects {}",
             columns.len(),
-            statement.column_count())
+            statement.column_count()))
     }
 
     let mismatched_columns: Vec<String> = statement
         .column_names()
         .iter()
         .zip(columns)
-        .filter_map(|(&a, &b)| { if a.eq_ignore_ascii_case(b) {
+        .filter_map(|(&a, &b)| { if !a.eq_ignore_ascii_case(b) {
             Some(format!("{} != {}", a, b))
         } else {
             None
         }})
         .collect();
     
-    if mismatched_columns.len() > 0 {
-        warn!(target: "sealion_parsing_events",
-            "Column name mismatch: {}",
-            mismatched_columns.join(", "))
+    if !mismatched_columns.is_empty() {
+        report_column_mismatch(format!("Column name mismatch: {}", mismatched_columns.join(", ")))
+    }
+}
+
+/// Logs a mismatch found by `check_columns` as a warning.
+///
+/// With the `strict_columns` feature, debug builds panic instead, so drift between a struct and its table fails
+/// tests rather than going unnoticed. Release builds always only log the warning, whether or not the feature is
+/// enabled.
+fn report_column_mismatch(message: String) {
+    if cfg!(all(feature = "strict_columns", debug_assertions)) {
+        panic!("{}", message);
+    }
+    warn!(target: "sealion_parsing_events", "{}", message);
+}
+
+fn check_required_columns(statement: &Statement, columns: &[&str]) -> SealionResult<()> {
+    let selected_columns = statement.column_names();
+    let missing_columns: Vec<String> = columns
+        .iter()
+        .filter(|&&column| !selected_columns.iter().any(|selected| selected.eq_ignore_ascii_case(column)))
+        .map(|column| column.to_string())
+        .collect();
+
+    if missing_columns.is_empty() {
+        Ok(())
+    } else {
+        Err(SealionError::MissingColumns(missing_columns))
+    }
+}
+
+/// How identifiers are quoted in generated SQL. See `set_quote_style`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum QuoteStyle {
+    /// `"name"`, as in standard SQL.
+    #[default]
+    DoubleQuote,
+    /// `` `name` ``, as in MySQL.
+    Backtick,
+    /// `[name]`, as in SQL Server.
+    Brackets
+}
+
+impl QuoteStyle {
+    /// Quotes `identifier` in this style, escaping any closing quote characters inside it.
+    pub fn quote(&self, identifier: &str) -> String {
+        match self {
+            QuoteStyle::DoubleQuote => format!("\"{}\"", identifier.replace('"', "\"\"")),
+            QuoteStyle::Backtick => format!("`{}`", identifier.replace('`', "``")),
+            QuoteStyle::Brackets => format!("[{}]", identifier.replace(']', "]]"))
+        }
+    }
+}
+
+static QUOTE_STYLE: AtomicU8 = AtomicU8::new(QuoteStyle::DoubleQuote as u8);
+
+/// Sets how identifiers are quoted in all SQL generated from now on, across every thread.
+pub fn set_quote_style(style: QuoteStyle) {
+    QUOTE_STYLE.store(style as u8, Ordering::Relaxed);
+}
+
+/// Returns how identifiers are currently quoted. Defaults to `QuoteStyle::DoubleQuote`.
+pub fn quote_style() -> QuoteStyle {
+    match QUOTE_STYLE.load(Ordering::Relaxed) {
+        1 => QuoteStyle::Backtick,
+        2 => QuoteStyle::Brackets,
+        _ => QuoteStyle::DoubleQuote
+    }
+}
+
+/// Quotes an identifier such as a table or column name so it can be safely embedded in SQL, using the
+/// current `quote_style`.
+pub fn quote_ident(identifier: &str) -> String {
+    quote_style().quote(identifier)
+}
+
+/// The direction to sort a column in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Ascending,
+    Descending
+}
+
+impl Direction {
+    fn as_sql(self) -> &'static str {
+        match self {
+            Direction::Ascending => "ASC",
+            Direction::Descending => "DESC"
+        }
+    }
+}
+
+/// Writes a WHERE clause joining `conditions` with AND. Nothing is written if there are no conditions.
+pub(crate) fn write_where_clause(sql_string: &mut String, conditions: &[String]) {
+    if let Some(where_string) = where_clause_sql(conditions) {
+        sql_string.push_str(&where_string);
+        sql_string.push(' ');
+    }
+}
+
+/// Renders `conditions` as a WHERE clause, or `None` if there are no conditions.
+fn where_clause_sql(conditions: &[String]) -> Option<String> {
+    conditions_clause_sql("WHERE", conditions)
+}
+
+/// Renders `conditions` joined with AND after `keyword`, or `None` if there are no conditions.
+fn conditions_clause_sql(keyword: &str, conditions: &[String]) -> Option<String> {
+    match conditions {
+        [] => None,
+        [condition] => Some(format!("{} {}", keyword, condition)),
+        _ => Some(format!("{} ({})", keyword, conditions.join(") AND (")))
     }
 }
 
+/// The clauses of a SELECT statement. They are declared in the order SQL requires them in, so sorting by
+/// `Clause` puts them in a valid order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Clause {
+    Select,
+    From,
+    Where,
+    GroupBy,
+    Having,
+    OrderBy,
+    Limit
+}
+
+/// Assembles a statement from its clauses in canonical order, whatever order they were added in.
+fn assemble_clauses(mut clauses: Vec<(Clause, String)>) -> String {
+    clauses.sort_by_key(|(clause, _)| *clause);
+    clauses.into_iter().map(|(_, sql)| sql + " ").collect()
+}
+
+/// A `Row` that is stored in a specific table.
+pub trait Table: Row {
+    /// Returns the name of the table rows of this type are stored in.
+    fn table_name<'a>() -> &'a str;
+
+    /// The version of the schema this type expects its table to have, checked by `check_schema_version`.
+    /// Bump it whenever a change to the type needs the table to be migrated.
+    const SCHEMA_VERSION: u32 = 1;
+
+    /// The type of the primary key column, which `SelectQuery::execute_with_keys` reads rows' keys into.
+    ///
+    /// Keyed collections only support single-column primary keys. For a composite primary key this is
+    /// ignored, and `execute_with_keys` fails rather than keying rows by only part of their key.
+    type Key: FromSql;
+
+    /// Returns the primary key columns of this table. Defaults to `id`.
+    ///
+    /// A table with more than one column in its primary key is created with a `PRIMARY KEY (a, b)` table
+    /// constraint, see `create_table_sql`.
+    fn primary_key<'a>() -> &'a [&'a str] {
+        &["id"]
+    }
+
+    /// Returns the column that marks a row as soft-deleted, if the table uses that convention.
+    /// A row is considered deleted when this column is not NULL, e.g. a `deleted_at` timestamp.
+    fn soft_delete_column<'a>() -> Option<&'a str> {
+        None
+    }
+
+    /// Returns the definitions of the columns of this table, used to generate its CREATE TABLE statement.
+    /// By default every column in `Row::columns()` is declared without a type.
+    fn column_defs() -> Vec<ColumnDef> {
+        Self::columns().iter().map(|column| ColumnDef::new(column, "")).collect()
+    }
+
+    /// Returns a query selecting from this table. Soft-deleted rows are excluded by default,
+    /// call `SelectQuery::with_deleted` to include them.
+    fn select() -> SelectQuery {
+        let mut query = SelectQuery::new(Self::table_name());
+        if let Some(column) = Self::soft_delete_column() {
+            query.exclude_soft_deleted(column);
+        }
+        query
+    }
+
+    /// Selects the row whose primary key is `key`, a tuple with a value for each column of `primary_key`, in the
+    /// same order. This renders as `WHERE a = ? AND b = ?`, and excludes soft-deleted rows like `select`.
+    ///
+    /// Fails with `SealionError::InvalidQuery` if `key` doesn't have as many values as the primary key has
+    /// columns.
+    fn find_by_pk<K: KeyValues>(connection: impl Deref<Target = Connection>, key: K) -> SealionResult<Option<Self>> {
+        let primary_key = Self::primary_key();
+        let key = key.key_values();
+        if key.len() != primary_key.len() {
+            return Err(SealionError::InvalidQuery(format!("Expected {} primary key values, got {}", primary_key.len(), key.len())));
+        }
+
+        let pairs: Vec<(&str, &dyn ToSql)> = primary_key.iter().copied().zip(key).collect();
+        let mut query = Self::select();
+        query.where_all(&pairs)?.limit(1);
+        Ok(query.execute(connection)?.pop())
+    }
+}
+
+/// The values of a primary key, given as a tuple such as `(1,)` or `(user_id, group_id)`. See
+/// `Table::find_by_pk`.
+pub trait KeyValues {
+    /// Returns the values of the key, in order.
+    fn key_values(&self) -> Vec<&dyn ToSql>;
+}
+
+macro_rules! impl_key_values {
+    ($($value:ident),+) => {
+        impl<$($value: ToSql),+> KeyValues for ($($value,)+) {
+            #[allow(non_snake_case)]
+            fn key_values(&self) -> Vec<&dyn ToSql> {
+                let ($($value,)+) = self;
+                vec![$($value as &dyn ToSql),+]
+            }
+        }
+    };
+}
+
+impl_key_values!(A);
+impl_key_values!(A, B);
+impl_key_values!(A, B, C);
+impl_key_values!(A, B, C, D);
+
 pub struct SelectQuery {
     pub table_name: String,
-    pub where_clause: Option<String>
+    pub alias: Option<String>,
+    pub joins: Vec<String>,
+    pub where_clause: Option<String>,
+    pub conditions: Vec<Condition>,
+    pub soft_delete_column: Option<String>,
+    pub select_exprs: Vec<String>,
+    /// The parameters bound by subqueries in `select_exprs`, which come before every other parameter.
+    pub select_params: Vec<Box<dyn ToSql>>,
+    pub group_by: Vec<String>,
+    pub having: Option<String>,
+    /// Conditions that groups must match in addition to `having`, whose parameters are bound after those of
+    /// the WHERE clause.
+    pub having_conditions: Vec<Condition>,
+    pub order_by: Vec<String>,
+    pub stable_order: bool,
+    pub limit: Option<u64>,
+    pub offset: Option<u64>,
+    pub numbered_placeholders: bool
 }
 
 impl SelectQuery {
     pub fn new<S: ToString>(table_name: S) -> Self {
         Self { 
             table_name: table_name.to_string(),
-            where_clause: None
+            alias: None,
+            joins: Vec::new(),
+            where_clause: None,
+            conditions: Vec::new(),
+            soft_delete_column: None,
+            select_exprs: Vec::new(),
+            select_params: Vec::new(),
+            group_by: Vec::new(),
+            having: None,
+            having_conditions: Vec::new(),
+            order_by: Vec::new(),
+            stable_order: false,
+            limit: None,
+            offset: None,
+            numbered_placeholders: false
         }
     }
 
-    pub fn r#where<S: ToString>(&mut self, where_clause: S) -> &mut Self {
-        self.where_clause = Some(where_clause.to_string());
+    /// Creates a query without a table, which renders without a FROM clause. Use `select_exprs` to set the
+    /// expressions to evaluate, e.g. `datetime('now') AS now`.
+    pub fn expression() -> Self {
+        Self::new("")
+    }
+
+    /// Applies the builder step `f` only if `condition` is true, which keeps dynamic queries fluent.
+    pub fn when(&mut self, condition: bool, f: impl FnOnce(&mut Self)) -> &mut Self {
+        if condition {
+            f(self);
+        }
         self
     }
 
-    pub fn build_sql_string(&self, columns: &[&str]) -> SealionResult<String> {
-        let mut sql_string = format!("SELECT {} ", columns.join(", "));
-        write!(sql_string, "FROM {} ", self.table_name)?;
-        
-        if let Some(where_string) = &self.where_clause {
-            write!(sql_string, "WHERE {}", where_string)?;
+    /// Applies the builder step `f` to the value in `option`, only if there is one.
+    ///
+    /// ```
+    /// # use sealion::SelectQuery;
+    /// let name_filter = Some("Apple");
+    /// let mut query = SelectQuery::new("fruit");
+    /// query.if_some(name_filter, |query, name| { query.where_eq("name", name); }).limit(10);
+    /// assert_eq!(query.build_sql_string(&["id"]), r#"SELECT id FROM fruit WHERE "name" = ? LIMIT 10 "#);
+    /// ```
+    pub fn if_some<T>(&mut self, option: Option<T>, f: impl FnOnce(&mut Self, T)) -> &mut Self {
+        if let Some(value) = option {
+            f(self, value);
         }
+        self
+    }
 
-        Ok(sql_string)
+    /// Gives the table an alias, rendering as `FROM t AS alias`. Columns can then be qualified with the alias,
+    /// which is needed to join the table with itself.
+    pub fn alias<S: ToString>(&mut self, alias: S) -> &mut Self {
+        self.alias = Some(alias.to_string());
+        self
     }
 
-    pub fn prepare_statement_columns<'conn>(&self, connection: &'conn Connection, columns: &[&str]) -> SealionResult<CachedStatement<'conn>> {
-        connection.prepare_cached(&self.build_sql_string(columns)?)
-            .map_err(|err| SealionError::RusqliteError(err))
+    /// Adds the raw SQL `join_clause` after the FROM clause, e.g. `LEFT JOIN t AS b ON b.id = a.parent_id`.
+    /// Joins are rendered in the order they were added.
+    pub fn join<S: ToString>(&mut self, join_clause: S) -> &mut Self {
+        self.joins.push(join_clause.to_string());
+        self
     }
 
-    pub fn prepare_statement<'conn, R: Row>(&self, connection: &'conn Connection) -> SealionResult<CachedStatement<'conn>> {
-        self.prepare_statement_columns(connection, R::columns())
+    /// Selects the raw SQL expressions in `select_exprs` instead of the columns of the row type.
+    ///
+    /// This allows selecting aggregates and other computed values. Each expression should be given an
+    /// alias matching a column of the row type, e.g. `COUNT(*) AS count`, since the selected columns are
+    /// checked against the aliases.
+    ///
+    /// Expressions are rendered verbatim, so window functions work the same way, for instance
+    /// `ROW_NUMBER() OVER (PARTITION BY category ORDER BY price) AS position`.
+    ///
+    /// This replaces every expression selected before, including subqueries added with `select_subquery`.
+    pub fn select_exprs<S: ToString>(&mut self, select_exprs: &[S]) -> &mut Self {
+        self.select_exprs = select_exprs.iter().map(|expr| expr.to_string()).collect();
+        self.select_params.clear();
+        self
     }
 
-    pub fn execute<R: Row>(&self, connection: &Connection) -> SealionResult<Vec<R>> {
-        let mut statement = self.prepare_statement::<R>(connection)?;
-        let rows_iterator = R::from_statement(&mut statement, [])?;
-        
-        rows_iterator.collect::<rusqlite::Result<Vec<R>>>()
-            .map_err(|err| SealionError::RusqliteError(err))
+    /// Adds `subquery` to `select_exprs`, selected as `alias`. It can refer to the columns of this query to
+    /// compute a value for each row, e.g. counting the rows of another table that refer to it.
+    ///
+    /// The parameters of the subquery are bound before those of this query. This isn't compatible with
+    /// `numbered_placeholders` unless the subquery binds no parameters. Fails with `SealionError::InvalidQuery`
+    /// if `subquery` doesn't select exactly one expression with `select_exprs`, or if `alias` is empty.
+    ///
+    /// ```
+    /// # use sealion::SelectQuery;
+    /// let mut children = SelectQuery::new("child");
+    /// children.select_exprs(&["COUNT(*)"]).r#where("child.parent_id = parent.id");
+    ///
+    /// let mut query = SelectQuery::new("parent");
+    /// query.select_exprs(&["id"]).select_subquery(children, "child_count")?;
+    /// assert_eq!(
+    ///     query.build_sql_string(&query.select_exprs.iter().map(String::as_str).collect::<Vec<_>>()),
+    ///     r#"SELECT id, (SELECT COUNT(*) FROM child WHERE child.parent_id = parent.id) AS "child_count" FROM parent "#
+    /// );
+    /// # Ok::<(), sealion::SealionError>(())
+    /// ```
+    pub fn select_subquery(&mut self, subquery: SelectQuery, alias: &str) -> SealionResult<&mut Self> {
+        if subquery.select_exprs.len() != 1 {
+            return Err(SealionError::InvalidQuery(format!("subquery must select exactly one column, not {}", subquery.select_exprs.len())));
+        }
+        if alias.is_empty() {
+            return Err(SealionError::InvalidQuery("subquery in the select list must have an alias".to_string()));
+        }
+
+        let hooked = subquery.hook_conditions();
+        let sql = subquery.build_sql_string_with(&subquery.selected_columns(&[]), &hooked);
+        self.select_exprs.push(format!("({}) AS {}", sql.trim_end(), quote_ident(alias)));
+        self.select_params.extend(subquery.params(&hooked).into_iter().map(to_value).collect::<rusqlite::Result<Vec<Value>>>()?
+            .into_iter()
+            .map(|value| Box::new(value) as Box<dyn ToSql>));
+        Ok(self)
     }
 
-    /// Similar to execute, but instead of failing-fast on collection, this method will instead iterate
-    /// through all the rows, attempt to parse them, and return every error and result.
-    pub fn execute_collect_errors<R: Row>(&self, connection: &Connection) -> SealionResult<(Vec<R>, Vec<SealionError>)> {
-        let mut statement = self.prepare_statement::<R>(connection)?;
-        
-        let mut parsing_errors: Vec<SealionError> = Vec::new();
-        let values: Vec<R> = R::from_statement(&mut statement, [])?
-            .filter_map(|result| match result {
-                Ok(row) => Some(row),
-                Err(err) => {
-                    parsing_errors.push(SealionError::RusqliteError(err));
-                    None
-                }
-            })
-            .collect();
-        
-        Ok((values, parsing_errors))
+    /// Adds the value at the JSON `path` of `column` to `select_exprs`, selected as `alias`. This renders as
+    /// `json_extract(column, 'path') AS alias`, so it's usually combined with `select_exprs` for the other
+    /// columns of the row.
+    ///
+    /// Fails with `SealionError::InvalidQuery` if `path` doesn't start with `$`.
+    pub fn select_json_extract(&mut self, column: &str, path: &str, alias: &str) -> SealionResult<&mut Self> {
+        self.select_exprs.push(format!("{} AS {}", json_extract(column, path)?, quote_ident(alias)));
+        Ok(self)
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use rusqlite::Connection;
+    /// Groups rows by each of the raw SQL expressions in `group_by`.
+    pub fn group_by<S: ToString>(&mut self, group_by: &[S]) -> &mut Self {
+        self.group_by = group_by.iter().map(|expr| expr.to_string()).collect();
+        self
+    }
+
+    /// Sets the HAVING clause used to filter groups.
+    pub fn having<S: ToString>(&mut self, having: S) -> &mut Self {
+        self.having = Some(having.to_string());
+        self
+    }
 
-    use crate::{Row, SelectQuery, SealionResult};
+    /// Adds a condition that groups must match, in addition to any other HAVING conditions, e.g.
+    /// `Condition { sql: "COUNT(*) > ?".to_string(), params: vec![Box::new(1)] }`. Its parameters are bound
+    /// after the parameters of the WHERE clause.
+    pub fn and_having(&mut self, condition: Condition) -> &mut Self {
+        self.having_conditions.push(condition);
+        self
+    }
 
-    #[derive(Debug, PartialEq, Eq)]
-    struct TestRow {
-        id: u64,
-        name: String,
-        optional: Option<String>
+    /// Creates a query selecting the rows that equal `example` in every column that isn't NULL.
+    /// See `RowValues::present_values`.
+    pub fn by_example<R: Table + RowValues>(example: &R) -> SealionResult<Self> {
+        let mut query = R::select();
+        for (column, value) in example.present_values()? {
+            query.and_where(Condition::eq(column, value));
+        }
+        Ok(query)
     }
 
-    impl Row for TestRow {
-        fn columns<'a>() -> &'a[ &'a str] {
-            &["id", "name", "optional"]
+    pub fn r#where<S: ToString>(&mut self, where_clause: S) -> &mut Self {
+        self.where_clause = Some(where_clause.to_string());
+        self
+    }
+
+    /// Same as `r#where`, without needing the raw identifier.
+    pub fn filter<S: ToString>(&mut self, where_clause: S) -> &mut Self {
+        self.r#where(where_clause)
+    }
+
+    /// Adds a condition that rows must match, in addition to any other conditions.
+    pub fn and_where(&mut self, condition: Condition) -> &mut Self {
+        self.conditions.push(condition);
+        self
+    }
+
+    /// Only selects rows where `column` equals `value`.
+    pub fn where_eq<T: ToSql + 'static>(&mut self, column: &str, value: T) -> &mut Self {
+        self.and_where(Condition::eq(column, value))
+    }
+
+    /// Only selects rows where every column equals its paired value, rendering as `col1 = ? AND col2 = ?`.
+    /// An empty slice adds no condition.
+    ///
+    /// The values are converted to owned `Value`s, which fails if one of them can't be converted.
+    pub fn where_all(&mut self, pairs: &[(&str, &dyn ToSql)]) -> SealionResult<&mut Self> {
+        if pairs.is_empty() {
+            return Ok(self);
         }
 
-        fn parse_row(row: &rusqlite::Row) -> rusqlite::Result<Self> {
-            Ok(Self { 
-                id: row.get(0)?,
-                name: row.get(1)?,
-                optional: row.get(2)?
-            })
+        let mut comparisons = Vec::with_capacity(pairs.len());
+        let mut params: Vec<Box<dyn ToSql>> = Vec::with_capacity(pairs.len());
+        for (column, value) in pairs {
+            comparisons.push(format!("{} = ?", quote_ident(column)));
+            params.push(Box::new(to_value(*value)?));
         }
+
+        Ok(self.and_where(Condition { sql: comparisons.join(" AND "), params }))
     }
 
-    fn setup_test_db() -> rusqlite::Result<Connection> {
-        let connection = rusqlite::Connection::open_in_memory()?;
-        connection.execute("CREATE TABLE test_table (id INTEGER PRIMARY KEY, name TEXT NOT NULL, optional TEXT)", [])?;
-        let rows_modified = connection.execute(r#" INSERT INTO test_table (id, name, optional) VALUES
-            (0, "Orange", "Strawberry"),
-            (1, "Apple", NULL),
-            (2, "Peach", "Raspberry")"#, [])?;
-        assert_eq!(rows_modified, 3, "Test data has not been created properly.");
-        Ok(connection)
+    /// Only selects rows where `column` equals `value`, treating `None` as IS NULL. See `Condition::opt_eq`.
+    pub fn where_opt_eq<T: ToSql + 'static>(&mut self, column: &str, value: Option<T>) -> &mut Self {
+        self.and_where(Condition::opt_eq(column, value))
     }
 
-    #[test]
-    fn select_low_level() -> SealionResult<()> {
-        let connection = setup_test_db()?;
-        
-        let rows: Vec<rusqlite::Result<TestRow>> = 
-            Row::from_statement(&mut connection.prepare("SELECT id, name, optional FROM test_table")?, [])?.collect();
-        assert_eq!(rows, vec![
-            Ok(TestRow { id: 0, name: "Orange".to_string(), optional: Some("Strawberry".to_string()) }),
-            Ok(TestRow { id: 1, name: "Apple".to_string(), optional: None }),
-            Ok(TestRow { id: 2, name: "Peach".to_string(), optional: Some("Raspberry".to_string()) })
-        ]);
+    /// Only selects rows where `column` is `value`, comparing NULLs as equal. See `Condition::is`.
+    ///
+    /// Use this instead of `where_eq` when the value may be NULL, since `column = NULL` never matches.
+    pub fn where_is<T: ToSql + 'static>(&mut self, column: &str, value: T) -> &mut Self {
+        self.and_where(Condition::is(column, value))
+    }
 
-        Ok(())
+    /// Only selects rows where `column` is not `value`, comparing NULLs as equal. See `Condition::is_not`.
+    pub fn where_is_not<T: ToSql + 'static>(&mut self, column: &str, value: T) -> &mut Self {
+        self.and_where(Condition::is_not(column, value))
     }
 
-    #[test]
-    fn select_with_query() -> SealionResult<()> {
-        let connection = setup_test_db()?;
+    /// Only selects rows where `column` matches the GLOB `pattern`. See `Condition::glob`.
+    pub fn where_glob<T: ToSql + 'static>(&mut self, column: &str, pattern: T) -> &mut Self {
+        self.and_where(Condition::glob(column, pattern))
+    }
 
-        let rows: Vec<TestRow> = SelectQuery::new("test_table").execute(&connection)?;
-        assert_eq!(rows, vec![
-            TestRow { id: 0, name: "Orange".to_string(), optional: Some("Strawberry".to_string()) },
-            TestRow { id: 1, name: "Apple".to_string(), optional: None },
-            TestRow { id: 2, name: "Peach".to_string(), optional: Some("Raspberry".to_string()) }
-        ]);
+    /// Only selects rows where `column` compares to `value` with `operator`, which is parsed from its SQL
+    /// form. Fails with `SealionError::UnknownOperator` if `operator` isn't one of `Operator`. See
+    /// `Condition::compare`.
+    pub fn where_dynamic(&mut self, column: &str, operator: &str, value: Value) -> SealionResult<&mut Self> {
+        let operator = operator.parse()?;
+        Ok(self.and_where(Condition::compare(column, operator, value)))
+    }
 
-        Ok(())
+    /// Only selects rows where `column` is one of the values selected by `subquery`. See `Condition::in_subquery`.
+    pub fn where_in_subquery(&mut self, column: &str, subquery: SelectQuery) -> SealionResult<&mut Self> {
+        Ok(self.and_where(Condition::in_subquery(column, subquery)?))
     }
 
-    #[test]
-    fn select_with_where_clause() -> SealionResult<()> {
-        let connection = setup_test_db()?;
+    /// Only selects rows where `column` is one of `values`. See `Condition::in_array` for how the values are
+    /// bound.
+    pub fn where_in_array(&mut self, column: &str, values: Vec<Value>) -> &mut Self {
+        self.and_where(Condition::in_array(column, values))
+    }
+
+    /// Only selects rows where `column` is one of `values`, bound as a single array. See `Condition::in_rarray`
+    /// for what the connection needs.
+    #[cfg(feature = "array")]
+    pub fn where_in_rarray(&mut self, column: &str, values: Vec<Value>) -> &mut Self {
+        self.and_where(Condition::in_rarray(column, values))
+    }
+
+    /// Only selects rows where the value at the JSON `path` of `column` equals `value`. See
+    /// `Condition::json_extract_eq`.
+    pub fn where_json_extract<T: ToSql + 'static>(&mut self, column: &str, path: &str, value: T) -> SealionResult<&mut Self> {
+        Ok(self.and_where(Condition::json_extract_eq(column, path, value)?))
+    }
+
+    /// Only selects rows where `column` is NULL.
+    pub fn where_null(&mut self, column: &str) -> &mut Self {
+        self.and_where(Condition::is_null(column))
+    }
+
+    /// Only selects rows where `column` is not NULL.
+    pub fn where_not_null(&mut self, column: &str) -> &mut Self {
+        self.and_where(Condition::is_not_null(column))
+    }
+
+    /// Excludes rows where `column` is not NULL, in addition to any other conditions.
+    pub fn exclude_soft_deleted<S: ToString>(&mut self, column: S) -> &mut Self {
+        self.soft_delete_column = Some(column.to_string());
+        self
+    }
+
+    /// Includes soft-deleted rows, undoing `exclude_soft_deleted`.
+    pub fn with_deleted(&mut self) -> &mut Self {
+        self.soft_delete_column = None;
+        self
+    }
+
+    /// Sets the ORDER BY clause to the raw SQL in `order_by`, replacing any previous ordering.
+    pub fn order_by<S: ToString>(&mut self, order_by: S) -> &mut Self {
+        self.order_by = vec![order_by.to_string()];
+        self
+    }
+
+    /// Orders by each of `columns` in turn, replacing any previous ordering.
+    pub fn order_by_columns(&mut self, columns: &[(&str, Direction)]) -> &mut Self {
+        self.order_by.clear();
+        for &(column, direction) in columns {
+            self.add_order_by(column, direction);
+        }
+        self
+    }
+
+    /// Orders by `column` after any previous ordering.
+    pub fn add_order_by(&mut self, column: &str, direction: Direction) -> &mut Self {
+        self.order_by.push(format!("{} {}", quote_ident(column), direction.as_sql()));
+        self
+    }
+
+    /// Orders by `column` compared with the collating sequence `collation` after any previous ordering.
+    ///
+    /// SQLite has the built-in collations `BINARY`, `NOCASE` and `RTRIM`, and others can be registered on
+    /// the connection.
+    pub fn add_order_by_collated(&mut self, column: &str, collation: &str, direction: Direction) -> &mut Self {
+        self.order_by.push(format!("{} COLLATE {} {}", quote_ident(column), quote_ident(collation), direction.as_sql()));
+        self
+    }
+
+    /// Appends the `rowid` to the ORDER BY clause as a final tiebreaker, so that rows which are equal in
+    /// every ordering column still come back in the same order every time. Without an ORDER BY clause, rows
+    /// are ordered by `rowid`.
+    ///
+    /// This keeps pages from LIMIT and OFFSET consistent. The table must have a rowid, so this fails for
+    /// `WITHOUT ROWID` tables. A table with an `INTEGER PRIMARY KEY` uses it as the rowid.
+    pub fn stable_order(&mut self, stable_order: bool) -> &mut Self {
+        self.stable_order = stable_order;
+        self
+    }
+
+    /// Limits the query to at most `limit` rows.
+    pub fn limit(&mut self, limit: u64) -> &mut Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Skips the first `offset` rows.
+    pub fn offset(&mut self, offset: u64) -> &mut Self {
+        self.offset = Some(offset);
+        self
+    }
+
+    /// Renders the placeholders of the conditions as numbered `?NNN` placeholders instead of anonymous `?`
+    /// ones, so a value used by several conditions is only bound once. Conditions must only use anonymous
+    /// placeholders for this to work.
+    ///
+    /// Only select and delete queries support this. `UpdateQuery` and `InsertQuery` always render anonymous
+    /// placeholders, since their assignments and values are bound by position ahead of any conditions.
+    pub fn numbered_placeholders(&mut self, enabled: bool) -> &mut Self {
+        self.numbered_placeholders = enabled;
+        self
+    }
+
+    /// Builds the SQL for this query selecting `columns`, including the conditions added by the select
+    /// hooks. See `add_select_hook`.
+    pub fn build_sql_string(&self, columns: &[&str]) -> String {
+        self.build_sql_string_with(columns, &self.hook_conditions())
+    }
+
+    /// Like `build_sql_string`, with the conditions added by the select hooks passed in as `hooked`.
+    fn build_sql_string_with(&self, columns: &[&str], hooked: &[Condition]) -> String {
+        let mut clauses = self.clauses(columns, &self.where_conditions(hooked), hooked);
+
+        match (self.limit, self.offset) {
+            (Some(limit), Some(offset)) => clauses.push((Clause::Limit, format!("LIMIT {} OFFSET {}", limit, offset))),
+            (Some(limit), None) => clauses.push((Clause::Limit, format!("LIMIT {}", limit))),
+            // SQLite doesn't allow OFFSET without LIMIT, a negative limit means there is none.
+            (None, Some(offset)) => clauses.push((Clause::Limit, format!("LIMIT -1 OFFSET {}", offset))),
+            (None, None) => {}
+        }
+
+        assemble_clauses(clauses)
+    }
+
+    /// Builds the query with its LIMIT and OFFSET replaced by placeholders, to be bound after the
+    /// parameters of the conditions.
+    fn build_paged_sql_string(&self, columns: &[&str], hooked: &[Condition]) -> String {
+        let mut clauses = self.clauses(columns, &self.where_conditions(hooked), hooked);
+        clauses.push((Clause::Limit, "LIMIT ? OFFSET ?".to_string()));
+        assemble_clauses(clauses)
+    }
+
+    /// Returns every clause of the query except LIMIT and OFFSET, with a WHERE clause made from `conditions`.
+    fn clauses(&self, columns: &[&str], conditions: &[String], hooked: &[Condition]) -> Vec<(Clause, String)> {
+        let mut clauses = vec![(Clause::Select, format!("SELECT {}", columns.join(", ")))];
+
+        if !self.table_name.is_empty() {
+            clauses.push((Clause::From, self.table_clause()));
+        }
+
+        if let Some(where_string) = where_clause_sql(conditions) {
+            clauses.push((Clause::Where, where_string));
+        }
+        if !self.group_by.is_empty() {
+            clauses.push((Clause::GroupBy, format!("GROUP BY {}", self.group_by.join(", "))));
+        }
+        if let Some(having_string) = conditions_clause_sql("HAVING", &self.having_conditions(hooked)) {
+            clauses.push((Clause::Having, having_string));
+        }
+        let mut order_by = self.order_by.clone();
+        if self.stable_order {
+            // Qualify the rowid when other tables are joined in, since they have one too
+            order_by.push(match (&self.alias, self.joins.is_empty()) {
+                (Some(alias), _) => format!("{}.rowid", alias),
+                (None, false) => format!("{}.rowid", self.table_name),
+                (None, true) => "rowid".to_string()
+            });
+        }
+        if !order_by.is_empty() {
+            clauses.push((Clause::OrderBy, format!("ORDER BY {}", order_by.join(", "))));
+        }
+
+        clauses
+    }
+
+    /// Returns the FROM clause, including the alias of the table and any joins.
+    fn table_clause(&self) -> String {
+        let mut from_string = format!("FROM {}", self.table_name);
+        if let Some(alias) = &self.alias {
+            from_string.push_str(&format!(" AS {}", alias));
+        }
+        for join in &self.joins {
+            from_string.push(' ');
+            from_string.push_str(join);
+        }
+        from_string
+    }
+
+    /// Returns the expressions to select, which are `columns` unless overridden by `select_exprs`.
+    fn selected_columns<'a>(&'a self, columns: &[&'a str]) -> Vec<&'a str> {
+        if self.select_exprs.is_empty() {
+            columns.to_vec()
+        } else {
+            self.select_exprs.iter().map(String::as_str).collect()
+        }
+    }
+
+    /// Returns every condition that makes up the WHERE clause of this query, followed by the conditions
+    /// added by the select hooks.
+    fn where_conditions(&self, hooked: &[Condition]) -> Vec<String> {
+        let mut conditions = Vec::new();
+
+        if let Some(where_string) = &self.where_clause {
+            conditions.push(where_string.clone());
+        }
+        if self.numbered_placeholders {
+            conditions.extend(number_placeholders(self.conditions.iter().chain(hooked)).0);
+        } else {
+            conditions.extend(self.conditions.iter().chain(hooked).map(|condition| condition.sql.clone()));
+        }
+        if let Some(column) = &self.soft_delete_column {
+            conditions.push(format!("{} IS NULL", quote_ident(column)));
+        }
+
+        conditions
+    }
+
+    /// Returns every condition that makes up the HAVING clause of this query.
+    fn having_conditions(&self, hooked: &[Condition]) -> Vec<String> {
+        let mut conditions: Vec<String> = self.having.iter().cloned().collect();
+        if self.numbered_placeholders {
+            // Numbers carry on from the WHERE clause, so a value used in both is only bound once
+            let where_count = self.conditions.len() + hooked.len();
+            conditions.extend(number_placeholders(self.conditions.iter().chain(hooked).chain(&self.having_conditions)).0.into_iter().skip(where_count));
+        } else {
+            conditions.extend(self.having_conditions.iter().map(|condition| condition.sql.clone()));
+        }
+        conditions
+    }
+
+    /// Returns the parameters bound by the conditions of this query and `hooked`, followed by those of the
+    /// HAVING conditions, in the order they appear in the SQL.
+    fn params<'a>(&'a self, hooked: &'a [Condition]) -> Vec<&'a dyn ToSql> {
+        self.params_with_cursor(hooked, None)
+    }
+
+    /// Returns the parameters bound by the WHERE clause alone, for statements that leave out GROUP BY and
+    /// HAVING.
+    fn where_params<'a>(&'a self, hooked: &'a [Condition]) -> Vec<&'a dyn ToSql> {
+        if self.numbered_placeholders {
+            return number_placeholders(self.conditions.iter().chain(hooked)).1;
+        }
+        self.conditions
+            .iter()
+            .chain(hooked)
+            .flat_map(|condition| condition.params.iter().map(|param| param.as_ref()))
+            .collect()
+    }
+
+    /// Like `params`, with `cursor` bound at the end of the WHERE clause, for keyset pagination.
+    fn params_with_cursor<'a>(&'a self, hooked: &'a [Condition], cursor: Option<&'a dyn ToSql>) -> Vec<&'a dyn ToSql> {
+        let mut params: Vec<&dyn ToSql> = self.select_params.iter().map(|param| param.as_ref()).collect();
+        params.extend(self.condition_params(hooked, cursor));
+        params
+    }
+
+    /// Like `params_with_cursor`, without the parameters of `select_params`, for statements that select other
+    /// columns than `select_exprs`.
+    fn condition_params<'a>(&'a self, hooked: &'a [Condition], cursor: Option<&'a dyn ToSql>) -> Vec<&'a dyn ToSql> {
+        let mut params = Vec::new();
+        if self.numbered_placeholders {
+            // The cursor placeholder is numbered after every other one
+            params.extend(number_placeholders(self.conditions.iter().chain(hooked).chain(&self.having_conditions)).1);
+            params.extend(cursor);
+            return params;
+        }
+
+        params.extend(self.where_params(hooked));
+        params.extend(cursor);
+        params.extend(self.having_conditions.iter().flat_map(|condition| condition.params.iter().map(|param| param.as_ref())));
+        params
+    }
+
+    /// Prepares the query selecting `columns`, returning the statement along with the parameters to bind to it.
+    ///
+    /// Like every other way of running the query, the statement includes the conditions added by the select
+    /// hooks, and the parameters include theirs, in the order of their placeholders.
+    pub fn prepare_statement_columns<'conn>(&self, connection: &'conn Connection, columns: &[&str]) -> SealionResult<(CachedStatement<'conn>, Vec<Value>)> {
+        let hooked = self.hook_conditions();
+        let statement = self.prepare_statement_with(connection, columns, &hooked)?;
+        let params = self.params(&hooked).into_iter().map(to_value).collect::<rusqlite::Result<Vec<Value>>>()?;
+        Ok((statement, params))
+    }
+
+    pub fn prepare_statement<'conn, R: Row>(&self, connection: &'conn Connection) -> SealionResult<(CachedStatement<'conn>, Vec<Value>)> {
+        self.prepare_statement_columns(connection, &self.selected_columns(R::columns()))
+    }
+
+    /// Like `prepare_statement_columns`, with the conditions added by the select hooks passed in as `hooked`.
+    fn prepare_statement_with<'conn>(&self, connection: &'conn Connection, columns: &[&str], hooked: &[Condition]) -> SealionResult<CachedStatement<'conn>> {
+        Ok(prepare_cached(connection, &self.build_sql_string_with(columns, hooked))?)
+    }
+
+    /// Runs the query and parses every row into `R`, failing on the first row that can't be parsed.
+    ///
+    /// The connection can be anything that dereferences to a `Connection`, which includes plain references
+    /// as well as guards like `Ref` and `MutexGuard`. This makes it straightforward to share a connection
+    /// through interior mutability:
+    ///
+    /// ```
+    /// # use std::{cell::RefCell, rc::Rc};
+    /// # use rusqlite::Connection;
+    /// # use sealion::{Row, SelectQuery};
+    /// # struct Name(String);
+    /// # impl Row for Name {
+    /// #     fn columns<'a>() -> &'a[&'a str] { &["name"] }
+    /// #     fn parse_row(row: &rusqlite::Row) -> rusqlite::Result<Self> { Ok(Self(row.get(0)?)) }
+    /// # }
+    /// let connection = Rc::new(RefCell::new(Connection::open_in_memory()?));
+    /// connection.borrow().execute_batch("CREATE TABLE fruit (name TEXT); INSERT INTO fruit VALUES ('Apple');")?;
+    ///
+    /// let names: Vec<Name> = SelectQuery::new("fruit").execute(connection.borrow())?;
+    /// # assert_eq!(names.len(), 1);
+    /// # Ok::<(), sealion::SealionError>(())
+    /// ```
+    pub fn execute<R: Row>(&self, connection: impl Deref<Target = Connection>) -> SealionResult<Vec<R>> {
+        self.execute_into(connection)
+    }
+
+    /// Like `execute`, but collects the rows into any collection `C`, such as a `HashSet` or a `Box<[R]>`.
+    ///
+    /// If a row can't be parsed, this fails with `SealionError::ParseError` giving the index of the row.
+    pub fn execute_into<R: Row, C: FromIterator<R>>(&self, connection: impl Deref<Target = Connection>) -> SealionResult<C> {
+        let hooked = self.hook_conditions();
+        let mut statement = self.prepare_statement_with(&connection, &self.selected_columns(R::columns()), &hooked)?;
+        check_columns(&statement, R::columns());
+
+        // Stepping through the rows and parsing them are kept apart, so only parse errors are given a row index
+        let mut rows = statement.query(params_from_iter(self.params(&hooked)))?;
+        let mut row_index = 0;
+        std::iter::from_fn(|| match rows.next() {
+                Ok(Some(row)) => {
+                    let index = row_index;
+                    row_index += 1;
+                    Some(R::parse_row(row).map_err(|err| SealionError::ParseError { row: index, source: Box::new(SealionError::from(err)) }))
+                },
+                Ok(None) => None,
+                Err(err) => Some(Err(SealionError::from(err)))
+            })
+            .collect()
+    }
+
+    /// Runs the query and folds every row into an accumulator with `f`, starting from `init`.
+    ///
+    /// Rows are parsed and passed to `f` one at a time without collecting them, so this suits reductions over
+    /// large results. Like `execute`, this stops at the first row that can't be parsed with
+    /// `SealionError::ParseError`.
+    pub fn fold<R: Row, B>(&self, connection: impl Deref<Target = Connection>, init: B, mut f: impl FnMut(B, R) -> B) -> SealionResult<B> {
+        let hooked = self.hook_conditions();
+        let mut statement = self.prepare_statement_with(&connection, &self.selected_columns(R::columns()), &hooked)?;
+        check_columns(&statement, R::columns());
+
+        let mut rows = statement.query(params_from_iter(self.params(&hooked)))?;
+        let mut accumulator = init;
+        let mut row_index = 0;
+        while let Some(row) = rows.next()? {
+            let value = R::parse_row(row).map_err(|err| SealionError::ParseError { row: row_index, source: Box::new(SealionError::from(err)) })?;
+            accumulator = f(accumulator, value);
+            row_index += 1;
+        }
+
+        Ok(accumulator)
+    }
+
+    /// Like `execute`, but binds the named parameters in the SQL, such as `:name` in a raw `r#where`, to the
+    /// values in `named`. The parameters of the conditions are bound as usual. Names are matched with or
+    /// without their prefix, so `:name` can be given as either `name` or `:name`.
+    ///
+    /// Fails with `SealionError::MissingParameters` if a named parameter has no value in `named`.
+    pub fn execute_named<R: Row>(&self, connection: impl Deref<Target = Connection>, named: &HashMap<String, Box<dyn ToSql>>) -> SealionResult<Vec<R>> {
+        let hooked = self.hook_conditions();
+        let mut statement = self.prepare_statement_with(&connection, &self.selected_columns(R::columns()), &hooked)?;
+        check_columns(&statement, R::columns());
+        bind_named_params(&mut statement, self.params(&hooked), named)?;
+
+        let mut rows = statement.raw_query();
+        let mut values = Vec::new();
+        while let Some(row) = rows.next()? {
+            let value = R::parse_row(row).map_err(|err| SealionError::ParseError { row: values.len(), source: Box::new(SealionError::from(err)) })?;
+            values.push(value);
+        }
+        Ok(values)
+    }
+
+    /// Like `execute`, but collects the rows into a `HashSet`, dropping any duplicates.
+    pub fn execute_set<R: Row + Eq + Hash>(&self, connection: impl Deref<Target = Connection>) -> SealionResult<HashSet<R>> {
+        self.execute_into(connection)
+    }
+
+    /// Like `execute`, but wraps each row in an `Arc` so it can be shared.
+    pub fn execute_arc<R: Row>(&self, connection: impl Deref<Target = Connection>) -> SealionResult<Vec<Arc<R>>> {
+        Ok(self.execute::<R>(connection)?.into_iter().map(Arc::new).collect())
+    }
+
+    /// Like `execute`, but wraps each row in an `Rc` so it can be shared.
+    pub fn execute_rc<R: Row>(&self, connection: impl Deref<Target = Connection>) -> SealionResult<Vec<Rc<R>>> {
+        Ok(self.execute::<R>(connection)?.into_iter().map(Rc::new).collect())
+    }
+
+    /// Returns the number of rows matching the conditions of this query.
+    pub fn count(&self, connection: impl Deref<Target = Connection>) -> SealionResult<u64> {
+        let hooked = self.hook_conditions();
+        let mut sql_string = format!("SELECT COUNT(*) {} ", self.table_clause());
+        write_where_clause(&mut sql_string, &self.where_conditions(&hooked));
+
+        let mut statement = prepare_cached(&connection, &sql_string)?;
+        Ok(statement.query_row(params_from_iter(self.where_params(&hooked)), |row| row.get(0))?)
+    }
+
+    /// Returns the number of rows this query selects with `columns`, ignoring its limit and offset, with the
+    /// conditions added by the select hooks passed in as `hooked`.
+    ///
+    /// Unlike `count`, the whole query is counted as a subquery, so GROUP BY and HAVING are taken into account.
+    fn count_with(&self, connection: &Connection, columns: &[&str], hooked: &[Condition]) -> SealionResult<u64> {
+        let mut clauses = self.clauses(columns, &self.where_conditions(hooked), hooked);
+        // The order doesn't change the count
+        clauses.retain(|(clause, _)| *clause != Clause::OrderBy);
+        let sql_string = format!("SELECT COUNT(*) FROM ({})", assemble_clauses(clauses).trim_end());
+
+        let mut statement = prepare_cached(connection, &sql_string)?;
+        Ok(statement.query_row(params_from_iter(self.params(hooked)), |row| row.get(0))?)
+    }
+
+    /// Returns the number of rows matching the conditions of this query for each distinct value of
+    /// `group_column`.
+    pub fn count_grouped<K: FromSql + Eq + Hash>(&self, connection: impl Deref<Target = Connection>, group_column: &str) -> SealionResult<HashMap<K, u64>> {
+        let group_column = quote_ident(group_column);
+        let hooked = self.hook_conditions();
+        let mut sql_string = format!("SELECT {}, COUNT(*) {} ", group_column, self.table_clause());
+        write_where_clause(&mut sql_string, &self.where_conditions(&hooked));
+        sql_string.push_str(&format!("GROUP BY {}", group_column));
+
+        let mut statement = prepare_cached(&connection, &sql_string)?;
+        let counts = statement.query_map(params_from_iter(self.where_params(&hooked)), |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<rusqlite::Result<HashMap<K, u64>>>()?;
+        Ok(counts)
+    }
+
+    /// Returns an iterator fetching the rows of this query in pages of `size` rows, using LIMIT and OFFSET.
+    ///
+    /// Each page is fetched with a separate query when the iterator advances, so only one page is held in
+    /// memory at a time. Iteration stops after the first page with fewer than `size` rows. Any limit or
+    /// offset set on the query is ignored. For deep pages over large tables, consider `after` instead.
+    pub fn chunks<'a, R: Row>(&'a self, connection: &'a Connection, size: u64) -> Chunks<'a, R> {
+        Chunks {
+            query: self,
+            connection,
+            size,
+            offset: 0,
+            exhausted: size == 0,
+            row_type: PhantomData
+        }
+    }
+
+    /// Like `execute`, but retries up to `attempts` times while the database is busy. See `retry_busy`.
+    pub fn execute_with_retry<R: Row>(&self, connection: impl Deref<Target = Connection>, attempts: u32, backoff: Duration) -> SealionResult<Vec<R>> {
+        retry_busy(attempts, backoff, || self.execute(&*connection))
+    }
+
+    /// Runs the query selecting `columns`, which can be in any order, and parses each row into `R` by name.
+    /// See `Row::from_statement_by_name` for how the selected columns are checked.
+    pub fn execute_by_name<R: Row>(&self, connection: impl Deref<Target = Connection>, columns: &[&str]) -> SealionResult<Vec<R>> {
+        let hooked = self.hook_conditions();
+        let mut statement = self.prepare_statement_with(&connection, columns, &hooked)?;
+        let rows_iterator = R::from_statement_by_name(&mut statement, params_from_iter(self.condition_params(&hooked, None)))?;
+
+        rows_iterator.collect::<rusqlite::Result<Vec<R>>>()
+            .map_err(SealionError::from)
+    }
+
+    /// Like `execute`, but pairs each row with the value of its primary key column. See `Table::primary_key`.
+    ///
+    /// The primary key is read by name, and is selected in addition to `R::columns()` if it isn't one of them.
+    /// Fails with `SealionError::InvalidQuery` if the primary key has more than one column, since `R::Key` only
+    /// holds one value and rows sharing their first key column would be indistinguishable.
+    pub fn execute_with_keys<R: Table>(&self, connection: impl Deref<Target = Connection>) -> SealionResult<Vec<(R::Key, R)>> {
+        let primary_key = match R::primary_key() {
+            [column] => *column,
+            columns => return Err(SealionError::InvalidQuery(format!("Expected a single primary key column, the key is {}", columns.join(", "))))
+        };
+
+        let mut columns = self.selected_columns(R::columns());
+        if !columns.contains(&primary_key) {
+            columns.push(primary_key);
+        }
+
+        let hooked = self.hook_conditions();
+        let mut statement = self.prepare_statement_with(&connection, &columns, &hooked)?;
+        check_columns(&statement, &columns);
+
+        let rows = statement.query_map(params_from_iter(self.params(&hooked)), |row| Ok((row.get(primary_key)?, R::parse_row(row)?)))?
+            .collect::<rusqlite::Result<Vec<(R::Key, R)>>>()?;
+        Ok(rows)
+    }
+
+    /// Runs the query selecting only `column`, and returns its value in every row.
+    pub fn execute_column<T: FromSql>(&self, connection: impl Deref<Target = Connection>, column: &str) -> SealionResult<Vec<T>> {
+        let hooked = self.hook_conditions();
+        let mut statement = self.prepare_statement_with(&connection, &[&quote_ident(column)], &hooked)?;
+        let values = statement.query_map(params_from_iter(self.condition_params(&hooked, None)), |row| get_idx(row, 0, column))?
+            .collect::<rusqlite::Result<Vec<T>>>()?;
+        Ok(values)
+    }
+
+    /// Runs the query and returns the first column of the first row, or `None` if no rows match.
+    ///
+    /// This selects `select_exprs`, or every column if there are none, and only fetches one row. `params`
+    /// are bound to the placeholders of the raw WHERE clause set with `r#where`, which come before those of
+    /// the conditions.
+    ///
+    /// Fails with `SealionError::InvalidQuery` if `params` isn't empty and the query has `select_params`, since
+    /// where those placeholders fall relative to the caller's can't be known.
+    ///
+    /// ```
+    /// # use rusqlite::Connection;
+    /// # use sealion::SelectQuery;
+    /// let connection = Connection::open_in_memory()?;
+    /// connection.execute_batch("CREATE TABLE config (key TEXT, value TEXT); INSERT INTO config VALUES ('theme', 'dark');")?;
+    ///
+    /// let mut query = SelectQuery::new("config");
+    /// query.select_exprs(&["value"]).r#where("key = ?");
+    /// assert_eq!(query.scalar_first::<String>(&connection, &[&"theme"])?, Some("dark".to_string()));
+    /// assert_eq!(query.scalar_first::<String>(&connection, &[&"font"])?, None);
+    /// # Ok::<(), sealion::SealionError>(())
+    /// ```
+    pub fn scalar_first<T: FromSql>(&self, connection: impl Deref<Target = Connection>, params: &[&dyn ToSql]) -> SealionResult<Option<T>> {
+        if !params.is_empty() && !self.select_params.is_empty() {
+            return Err(SealionError::InvalidQuery("params can't be combined with the parameters of select_params".to_string()));
+        }
+
+        let hooked = self.hook_conditions();
+        let mut clauses = self.clauses(&self.selected_columns(&["*"]), &self.where_conditions(&hooked), &hooked);
+        clauses.push((Clause::Limit, format!("LIMIT 1 OFFSET {}", self.offset.unwrap_or(0))));
+
+        let mut statement = prepare_cached(&connection, &assemble_clauses(clauses))?;
+        let all_params = params.iter().copied().chain(self.params(&hooked));
+        Ok(statement.query_row(params_from_iter(all_params), |row| row.get(0)).optional()?)
+    }
+
+    /// Like `execute_column`, but skips the rows where `column` is NULL.
+    pub fn execute_column_non_null<T: FromSql>(&self, connection: impl Deref<Target = Connection>, column: &str) -> SealionResult<Vec<T>> {
+        let values: Vec<Option<T>> = self.execute_column(connection, column)?;
+        Ok(values.into_iter().flatten().collect())
+    }
+
+    /// Runs the query for its side effects, stepping through every row without parsing any of them.
+    ///
+    /// This selects `select_exprs`, or every column if there are none, so there's no need for a `Row` type
+    /// to throw the results into. Useful when the selected expressions do the work, such as calling a
+    /// function for each matching row.
+    pub fn execute_discard(&self, connection: impl Deref<Target = Connection>) -> SealionResult<()> {
+        let hooked = self.hook_conditions();
+        let mut statement = self.prepare_statement_with(&connection, &self.selected_columns(&["*"]), &hooked)?;
+        let mut rows = statement.query(params_from_iter(self.params(&hooked)))?;
+        while rows.next()?.is_some() {}
+
+        Ok(())
+    }
+
+    /// Fetches one page of rows using keyset pagination.
+    ///
+    /// Rows are ordered by `column` and only those where `column` is greater than `value` are selected, up
+    /// to `page_size` rows. Unlike LIMIT/OFFSET, the database can seek straight to the start of the page
+    /// using an index on `column`, so deep pages are as cheap as the first.
+    ///
+    /// Returns the rows along with the value of `column` in the last row, which should be passed as `value`
+    /// to fetch the next page. The cursor is `None` when the page is empty. `column` must be one of
+    /// `R::columns()` so the cursor can be read back.
+    pub fn after<R: Row>(&self, connection: impl Deref<Target = Connection>, column: &str, value: impl ToSql, page_size: u64) -> SealionResult<(Vec<R>, Option<Value>)> {
+        let hooked = self.hook_conditions();
+        let mut statement = prepare_cached(&connection, &self.build_keyset_sql_string(&self.selected_columns(R::columns()), column, page_size, false, &hooked))?;
+        check_columns(&statement, R::columns());
+
+        let mut rows: Vec<R> = Vec::new();
+        let mut cursor: Option<Value> = None;
+        let params = self.params_with_cursor(&hooked, Some(&value));
+        let mut mapped_rows = statement.query_map(params_from_iter(params), |row| Ok((R::parse_row(row)?, row.get::<_, Value>(column)?)))?;
+        for result in &mut mapped_rows {
+            let (row, row_cursor) = result?;
+            rows.push(row);
+            cursor = Some(row_cursor);
+        }
+
+        Ok((rows, cursor))
+    }
+
+    /// Builds the query for a page of keyset pagination. Rows are limited to those after a cursor, which is
+    /// bound after the parameters of the conditions, unless `first_page` is true.
+    fn build_keyset_sql_string(&self, columns: &[&str], column: &str, page_size: u64, first_page: bool, hooked: &[Condition]) -> String {
+        let column = quote_ident(column);
+        let mut conditions = self.where_conditions(hooked);
+        if !first_page {
+            // With numbered placeholders the cursor gets the number after every other parameter, since an
+            // anonymous one would take a number the HAVING clause may already use
+            let placeholder = if self.numbered_placeholders {
+                format!("?{}", number_placeholders(self.conditions.iter().chain(hooked).chain(&self.having_conditions)).1.len() + 1)
+            } else {
+                "?".to_string()
+            };
+            conditions.push(format!("{} > {}", column, placeholder));
+        }
+
+        let mut clauses = self.clauses(columns, &conditions, hooked);
+        clauses.retain(|(clause, _)| *clause != Clause::OrderBy);
+        clauses.push((Clause::OrderBy, format!("ORDER BY {}", column)));
+        clauses.push((Clause::Limit, format!("LIMIT {}", page_size)));
+
+        assemble_clauses(clauses)
+    }
+
+    /// Fetches page number `page` (starting from 0) of `size` rows, along with the total number of rows
+    /// matching this query.
+    ///
+    /// The count is taken over the same query as the page, so `total` always describes the rows being paged
+    /// through, including with a GROUP BY. The query's own limit and offset are ignored. Fails with
+    /// `SealionError::InvalidQuery` if the offset of the page doesn't fit in a `u64`.
+    pub fn execute_paged<R: Row>(&self, connection: impl Deref<Target = Connection>, page: u64, size: u64) -> SealionResult<Page<R>> {
+        let offset = page.checked_mul(size)
+            .ok_or_else(|| SealionError::InvalidQuery(format!("page {} of size {} is out of range", page, size)))?;
+        let hooked = self.hook_conditions();
+        let columns = self.selected_columns(R::columns());
+        let mut statement = prepare_cached(&connection, &self.build_paged_sql_string(&columns, &hooked))?;
+        let mut params = self.params(&hooked);
+        params.push(&size);
+        params.push(&offset);
+
+        let rows = R::from_statement(&mut statement, params_from_iter(params))?
+            .collect::<rusqlite::Result<Vec<R>>>()?;
+        let total = self.count_with(&connection, &columns, &hooked)?;
+
+        Ok(Page { rows, total, page, size })
+    }
+
+    /// Similar to execute, but instead of failing-fast on collection, this method will instead iterate
+    /// through all the rows, attempt to parse them, and return every error and result.
+    pub fn execute_collect_errors<R: Row>(&self, connection: impl Deref<Target = Connection>) -> SealionResult<(Vec<R>, Vec<SealionError>)> {
+        self.execute_collect_errors_columns(connection, R::columns())
+    }
+
+    /// Like `execute_collect_errors`, but selects `columns` instead of `R::columns()`, e.g. for a struct that
+    /// only reads some of the columns of the table.
+    ///
+    /// Unless `columns` is `R::columns()`, the selected columns aren't checked against it, since they're
+    /// expected to differ.
+    pub fn execute_collect_errors_columns<R: Row>(&self, connection: impl Deref<Target = Connection>, columns: &[&str]) -> SealionResult<(Vec<R>, Vec<SealionError>)> {
+        let hooked = self.hook_conditions();
+        let mut statement = self.prepare_statement_with(&connection, &self.selected_columns(columns), &hooked)?;
+        if columns == R::columns() {
+            check_columns(&statement, columns);
+        }
+
+        let mut parsing_errors: Vec<SealionError> = Vec::new();
+        let values: Vec<R> = R::from_statement_with(&mut statement, params_from_iter(self.params(&hooked)), R::parse_row)?
+            .filter_map(|result| match result {
+                Ok(row) => Some(row),
+                Err(err) => {
+                    parsing_errors.push(SealionError::from(err));
+                    None
+                }
+            })
+            .collect();
+        
+        Ok((values, parsing_errors))
+    }
+}
+
+/// One page of the rows matching a query. See `SelectQuery::execute_paged`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Page<R> {
+    pub rows: Vec<R>,
+    /// The number of rows matching the query across every page.
+    pub total: u64,
+    pub page: u64,
+    pub size: u64
+}
+
+/// An iterator over the pages of a query. See `SelectQuery::chunks`.
+pub struct Chunks<'a, R> {
+    query: &'a SelectQuery,
+    connection: &'a Connection,
+    size: u64,
+    offset: u64,
+    exhausted: bool,
+    row_type: PhantomData<fn() -> R>
+}
+
+impl<'a, R: Row> Chunks<'a, R> {
+    fn fetch_page(&self) -> SealionResult<Vec<R>> {
+        let hooked = self.query.hook_conditions();
+        let sql_string = self.query.build_paged_sql_string(&self.query.selected_columns(R::columns()), &hooked);
+        let mut statement = prepare_cached(self.connection, &sql_string)?;
+        let mut params = self.query.params(&hooked);
+        params.push(&self.size);
+        params.push(&self.offset);
+
+        let rows = R::from_statement(&mut statement, params_from_iter(params))?
+            .collect::<rusqlite::Result<Vec<R>>>()?;
+        Ok(rows)
+    }
+}
+
+impl<'a, R: Row> Iterator for Chunks<'a, R> {
+    type Item = SealionResult<Vec<R>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.exhausted {
+            return None;
+        }
+
+        let page = self.fetch_page();
+        match &page {
+            Ok(rows) if (rows.len() as u64) < self.size => self.exhausted = true,
+            Ok(rows) => self.offset += rows.len() as u64,
+            Err(_) => self.exhausted = true
+        }
+
+        match page {
+            Ok(rows) if rows.is_empty() => None,
+            page => Some(page)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::collections::{HashMap, HashSet};
+    use std::rc::Rc;
+    use std::sync::Arc;
+
+    use rusqlite::{Connection, ToSql};
+    use rusqlite::types::Value;
+
+    use crate::{get_named, parse_tagged, Condition, Direction, InsertQuery, Operator, Page, QuoteStyle, Row, RowValues, SelectQuery, SealionError, SealionResult, Table};
+
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub struct TestRow {
+        pub id: u64,
+        pub name: String,
+        pub optional: Option<String>
+    }
+
+    impl Row for TestRow {
+        fn columns<'a>() -> &'a[ &'a str] {
+            &["id", "name", "optional"]
+        }
+
+        fn parse_row(row: &rusqlite::Row) -> rusqlite::Result<Self> {
+            Ok(Self { 
+                id: row.get(0)?,
+                name: row.get(1)?,
+                optional: row.get(2)?
+            })
+        }
+    }
+
+    impl RowValues for TestRow {
+        fn to_params(&self) -> Vec<&dyn ToSql> {
+            vec![&self.id, &self.name, &self.optional]
+        }
+    }
+
+    impl Table for TestRow {
+        type Key = u64;
+
+        fn table_name<'a>() -> &'a str {
+            "test_table"
+        }
+    }
+
+    /// A row that is parsed by column name rather than position.
+    #[derive(Debug, PartialEq, Eq)]
+    pub struct NamedRow {
+        pub id: u64,
+        pub name: String
+    }
+
+    impl Row for NamedRow {
+        fn columns<'a>() -> &'a[&'a str] {
+            &["id", "name"]
+        }
+
+        fn parse_row(row: &rusqlite::Row) -> rusqlite::Result<Self> {
+            Ok(Self {
+                id: row.get(Self::column_index(row, "id")?)?,
+                name: row.get(Self::column_index(row, "name")?)?
+            })
+        }
+    }
+
+    /// A database file in the temp directory that is deleted when dropped.
+    pub struct TempDatabase {
+        pub path: std::path::PathBuf
+    }
+
+    impl TempDatabase {
+        pub fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("sealion_{}_{}.db", name, std::process::id()));
+            let _ = std::fs::remove_file(&path);
+            Self { path }
+        }
+    }
+
+    impl Drop for TempDatabase {
+        fn drop(&mut self) {
+            for suffix in ["", "-wal", "-shm"] {
+                let mut path = self.path.clone().into_os_string();
+                path.push(suffix);
+                let _ = std::fs::remove_file(path);
+            }
+        }
+    }
+
+    pub fn setup_test_db() -> rusqlite::Result<Connection> {
+        let connection = rusqlite::Connection::open_in_memory()?;
+        connection.execute("CREATE TABLE test_table (id INTEGER PRIMARY KEY, name TEXT NOT NULL, optional TEXT)", [])?;
+        let rows_modified = connection.execute(r#" INSERT INTO test_table (id, name, optional) VALUES
+            (0, "Orange", "Strawberry"),
+            (1, "Apple", NULL),
+            (2, "Peach", "Raspberry")"#, [])?;
+        assert_eq!(rows_modified, 3, "Test data has not been created properly.");
+        Ok(connection)
+    }
+
+    #[test]
+    fn collect_errors_with_columns() -> SealionResult<()> {
+        let connection = setup_test_db()?;
+
+        // The id of the first row becomes -1, which can't be parsed as a u64
+        let (rows, errors): (Vec<NamedRow>, _) = SelectQuery::new("test_table")
+            .execute_collect_errors_columns(&connection, &["id - 1 AS id", "name"])?;
+        assert_eq!(rows, vec![
+            NamedRow { id: 0, name: "Apple".to_string() },
+            NamedRow { id: 1, name: "Peach".to_string() }
+        ]);
+        assert_eq!(errors.len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_json_paths() -> SealionResult<()> {
+        let connection = setup_test_db()?;
+        connection.execute_batch(r#"
+            ALTER TABLE test_table ADD COLUMN data TEXT;
+            UPDATE test_table SET data = '{"colour": "orange", "size": {"width": 8}}' WHERE id = 0;
+            UPDATE test_table SET data = '{"colour": "red", "size": {"width": 7}}' WHERE id = 1;
+        "#)?;
+
+        #[derive(Row, Debug, PartialEq)]
+        struct Width {
+            id: u64,
+            width: i64
+        }
+
+        let mut query = SelectQuery::new("test_table");
+        query
+            .select_exprs(&["id"])
+            .select_json_extract("data", "$.size.width", "width")?
+            .where_json_extract("data", "$.colour", "red")?;
+        assert_eq!(query.select_exprs, vec!["id", r#"json_extract("data", '$.size.width') AS "width""#]);
+        assert_eq!(query.conditions[0].sql, r#"json_extract("data", '$.colour') = ?"#);
+
+        let rows: Vec<Width> = query.execute(&connection)?;
+        assert_eq!(rows, vec![Width { id: 1, width: 7 }]);
+
+        assert!(matches!(query.where_json_extract("data", "colour", "red"), Err(SealionError::InvalidQuery(_))));
+        assert!(matches!(query.select_json_extract("data", "size')", "width"), Err(SealionError::InvalidQuery(_))));
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_low_level() -> SealionResult<()> {
+        let connection = setup_test_db()?;
+        
+        let rows: Vec<rusqlite::Result<TestRow>> = 
+            Row::from_statement(&mut connection.prepare("SELECT id, name, optional FROM test_table")?, [])?.collect();
+        assert_eq!(rows, vec![
+            Ok(TestRow { id: 0, name: "Orange".to_string(), optional: Some("Strawberry".to_string()) }),
+            Ok(TestRow { id: 1, name: "Apple".to_string(), optional: None }),
+            Ok(TestRow { id: 2, name: "Peach".to_string(), optional: Some("Raspberry".to_string()) })
+        ]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_with_query() -> SealionResult<()> {
+        let connection = setup_test_db()?;
+
+        let rows: Vec<TestRow> = SelectQuery::new("test_table").execute(&connection)?;
+        assert_eq!(rows, vec![
+            TestRow { id: 0, name: "Orange".to_string(), optional: Some("Strawberry".to_string()) },
+            TestRow { id: 1, name: "Apple".to_string(), optional: None },
+            TestRow { id: 2, name: "Peach".to_string(), optional: Some("Raspberry".to_string()) }
+        ]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_with_where_clause() -> SealionResult<()> {
+        let connection = setup_test_db()?;
+
+        let rows: Vec<TestRow> = SelectQuery::new("test_table")
+            .r#where("optional IS NOT NULL").execute(&connection)?;
+        assert_eq!(rows, vec![
+            TestRow { id: 0, name: "Orange".to_string(), optional: Some("Strawberry".to_string()) },
+            TestRow { id: 2, name: "Peach".to_string(), optional: Some("Raspberry".to_string()) }
+        ]);
+
+        let filtered_rows: Vec<TestRow> = SelectQuery::new("test_table")
+            .filter("optional IS NOT NULL").execute(&connection)?;
+        assert_eq!(filtered_rows, rows);
+        Ok(())
+    }
+
+    #[test]
+    // The columns are selected out of order, which strict_columns turns into a panic
+    #[cfg(not(all(feature = "strict_columns", debug_assertions)))]
+    fn parse_by_column_name() -> SealionResult<()> {
+        let connection = setup_test_db()?;
+
+        let rows: Vec<(u64, String)> =
+            NamedRow::from_statement(&mut connection.prepare("SELECT name, id FROM test_table WHERE id = 1")?, [])?
+                .map(|row| row.map(|row| (row.id, row.name)))
+                .collect::<rusqlite::Result<_>>()?;
+        assert_eq!(rows, vec![(1, "Apple".to_string())]);
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(all(feature = "strict_columns", debug_assertions))]
+    #[should_panic(expected = "Column name mismatch: name != id, id != name")]
+    fn strict_columns_panic_on_mismatch() {
+        let connection = setup_test_db().unwrap();
+        let _ = NamedRow::from_statement(&mut connection.prepare("SELECT name, id FROM test_table").unwrap(), []);
+    }
+
+    #[test]
+    fn select_keyset_pages() -> SealionResult<()> {
+        let connection = setup_test_db()?;
+        let query = SelectQuery::new("test_table");
+
+        let (first_page, cursor) = query.after::<TestRow>(&connection, "id", -1, 2)?;
+        assert_eq!(first_page, vec![
+            TestRow { id: 0, name: "Orange".to_string(), optional: Some("Strawberry".to_string()) },
+            TestRow { id: 1, name: "Apple".to_string(), optional: None }
+        ]);
+        assert_eq!(cursor, Some(Value::Integer(1)));
+
+        let (second_page, cursor) = query.after::<TestRow>(&connection, "id", cursor, 2)?;
+        assert_eq!(second_page, vec![
+            TestRow { id: 2, name: "Peach".to_string(), optional: Some("Raspberry".to_string()) }
+        ]);
+        assert_eq!(cursor, Some(Value::Integer(2)));
+
+        let (last_page, cursor) = query.after::<TestRow>(&connection, "id", cursor, 2)?;
+        assert!(last_page.is_empty());
+        assert_eq!(cursor, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_keyset_page_with_numbered_placeholders() -> SealionResult<()> {
+        let connection = setup_test_db()?;
+        connection.execute(r#"INSERT INTO test_table (id, name) VALUES (3, "Apple")"#, [])?;
+
+        let mut query = SelectQuery::new("test_table");
+        query
+            .numbered_placeholders(true)
+            .select_exprs(&["MIN(id) AS id", "name"])
+            .and_where(Condition::compare("id", Operator::Ge, Value::Integer(0)))
+            .group_by(&["name"])
+            .and_having(Condition { sql: "COUNT(*) >= ?".to_string(), params: vec![Box::new(1)] });
+        assert_eq!(
+            query.build_keyset_sql_string(&query.selected_columns(&[]), "name", 5, false, &[]),
+            r#"SELECT MIN(id) AS id, name FROM test_table WHERE ("id" >= ?1) AND ("name" > ?3) GROUP BY name HAVING COUNT(*) >= ?2 ORDER BY "name" LIMIT 5 "#
+        );
+
+        let (rows, cursor) = query.after::<NamedRow>(&connection, "name", "Apple", 5)?;
+        assert_eq!(rows, vec![NamedRow { id: 0, name: "Orange".to_string() }, NamedRow { id: 2, name: "Peach".to_string() }]);
+        assert_eq!(cursor, Some(Value::Text("Peach".to_string())));
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_excluding_soft_deleted() -> SealionResult<()> {
+        #[derive(Debug, PartialEq, Eq)]
+        struct SoftDeletableRow(TestRow);
+
+        impl Row for SoftDeletableRow {
+            fn columns<'a>() -> &'a[&'a str] {
+                TestRow::columns()
+            }
+
+            fn parse_row(row: &rusqlite::Row) -> rusqlite::Result<Self> {
+                TestRow::parse_row(row).map(Self)
+            }
+        }
+
+        impl Table for SoftDeletableRow {
+            type Key = u64;
+
+            fn table_name<'a>() -> &'a str {
+                "test_table"
+            }
+
+            fn soft_delete_column<'a>() -> Option<&'a str> {
+                Some("optional")
+            }
+        }
+
+        let connection = setup_test_db()?;
+
+        assert_eq!(SoftDeletableRow::select().build_sql_string(&["id"]), r#"SELECT id FROM test_table WHERE "optional" IS NULL "#);
+        let rows: Vec<SoftDeletableRow> = SoftDeletableRow::select().execute(&connection)?;
+        assert_eq!(rows, vec![
+            SoftDeletableRow(TestRow { id: 1, name: "Apple".to_string(), optional: None })
+        ]);
+
+        let rows: Vec<TestRow> = SoftDeletableRow::select().r#where("id > 0").with_deleted().execute(&connection)?;
+        assert_eq!(rows, vec![
+            TestRow { id: 1, name: "Apple".to_string(), optional: None },
+            TestRow { id: 2, name: "Peach".to_string(), optional: Some("Raspberry".to_string()) }
+        ]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_with_shared_connection() -> SealionResult<()> {
+        let connection = Rc::new(RefCell::new(setup_test_db()?));
+
+        let rows: Vec<TestRow> = SelectQuery::new("test_table").r#where("id = 1").execute(connection.borrow())?;
+        assert_eq!(rows, vec![
+            TestRow { id: 1, name: "Apple".to_string(), optional: None }
+        ]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_where_null() -> SealionResult<()> {
+        let connection = setup_test_db()?;
+
+        let rows: Vec<TestRow> = SelectQuery::new("test_table").where_null("optional").execute(&connection)?;
+        assert_eq!(rows, vec![
+            TestRow { id: 1, name: "Apple".to_string(), optional: None }
+        ]);
+
+        let rows: Vec<TestRow> = SelectQuery::new("test_table").where_not_null("optional").execute(&connection)?;
+        assert_eq!(rows, vec![
+            TestRow { id: 0, name: "Orange".to_string(), optional: Some("Strawberry".to_string()) },
+            TestRow { id: 2, name: "Peach".to_string(), optional: Some("Raspberry".to_string()) }
+        ]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_order_by_columns() -> SealionResult<()> {
+        let connection = setup_test_db()?;
+
+        let mut query = SelectQuery::new("test_table");
+        query.order_by("id").order_by_columns(&[("optional", Direction::Descending), ("name", Direction::Ascending)]);
+        assert_eq!(
+            query.build_sql_string(&["id"]),
+            r#"SELECT id FROM test_table ORDER BY "optional" DESC, "name" ASC "#
+        );
+
+        let rows: Vec<TestRow> = query.execute(&connection)?;
+        assert_eq!(rows, vec![
+            TestRow { id: 0, name: "Orange".to_string(), optional: Some("Strawberry".to_string()) },
+            TestRow { id: 2, name: "Peach".to_string(), optional: Some("Raspberry".to_string()) },
+            TestRow { id: 1, name: "Apple".to_string(), optional: None }
+        ]);
+
+        query.add_order_by("id", Direction::Descending);
+        assert_eq!(
+            query.build_sql_string(&["id"]),
+            r#"SELECT id FROM test_table ORDER BY "optional" DESC, "name" ASC, "id" DESC "#
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_ordered_with_collation() -> SealionResult<()> {
+        let connection = setup_test_db()?;
+        connection.execute("INSERT INTO test_table (id, name) VALUES (3, 'banana')", [])?;
+
+        let mut query = SelectQuery::new("test_table");
+        query.add_order_by_collated("name", "NOCASE", Direction::Ascending);
+        assert_eq!(
+            query.build_sql_string(&["name"]),
+            r#"SELECT name FROM test_table ORDER BY "name" COLLATE "NOCASE" ASC "#
+        );
+
+        let rows: Vec<TestRow> = query.execute(&connection)?;
+        let names: Vec<&str> = rows.iter().map(|row| row.name.as_str()).collect();
+        assert_eq!(names, vec!["Apple", "banana", "Orange", "Peach"]);
+
+        // Without the collation, uppercase letters sort before lowercase ones
+        let rows: Vec<TestRow> = SelectQuery::new("test_table").order_by_columns(&[("name", Direction::Ascending)]).execute(&connection)?;
+        assert_eq!(rows.last().map(|row| row.name.as_str()), Some("banana"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn column_parse_errors_name_the_column() -> SealionResult<()> {
+        #[derive(Row, Debug)]
+        #[allow(dead_code)]
+        struct BadRow {
+            id: u64,
+            name: i64
+        }
+
+        let connection = setup_test_db()?;
+
+        let result = SelectQuery::new("test_table").execute::<BadRow>(&connection);
+        match result {
+            Err(SealionError::ParseError { row: 0, source }) => match *source {
+                SealionError::ColumnParse(err) => {
+                    assert_eq!(err.column, "name");
+                    assert_eq!(err.expected, "i64");
+                },
+                other => panic!("Expected a column parse error, got {:?}", other)
+            },
+            other => panic!("Expected a parse error, got {:?}", other)
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn fold_rows() -> SealionResult<()> {
+        let connection = setup_test_db()?;
+
+        let total_length = SelectQuery::new("test_table").fold(&connection, 0, |total, row: TestRow| total + row.name.len())?;
+        assert_eq!(total_length, 16);
+
+        let longest = SelectQuery::new("test_table").where_not_null("optional").fold(&connection, None, |longest: Option<TestRow>, row: TestRow| {
+            match longest {
+                Some(longest) if longest.name.len() >= row.name.len() => Some(longest),
+                _ => Some(row)
+            }
+        })?;
+        assert_eq!(longest.map(|row| row.id), Some(0));
+
+        connection.execute("UPDATE test_table SET id = -1 WHERE name = 'Peach'", [])?;
+        let mut seen = 0;
+        let result = SelectQuery::new("test_table").fold(&connection, (), |_, _: TestRow| seen += 1);
+        assert!(matches!(result, Err(SealionError::ParseError { row: 0, .. })));
+        assert_eq!(seen, 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_errors_give_the_row_index() -> SealionResult<()> {
+        let connection = setup_test_db()?;
+        connection.execute("UPDATE test_table SET id = -1 WHERE name = 'Peach'", [])?;
+
+        let result = SelectQuery::new("test_table").execute::<TestRow>(&connection);
+        assert!(matches!(result, Err(SealionError::ParseError { row: 0, .. })));
+
+        let mut query = SelectQuery::new("test_table");
+        query.order_by_columns(&[("name", Direction::Ascending)]);
+        let err = query.execute::<TestRow>(&connection).unwrap_err();
+        assert!(matches!(err, SealionError::ParseError { row: 2, .. }));
+        assert!(err.to_string().starts_with("Failed to parse row 2: "), "{}", err);
+        assert_eq!(err.user_message(), "An internal error occurred");
+
+        Ok(())
+    }
+
+    #[test]
+    fn every_clause_in_canonical_order() {
+        let mut query = SelectQuery::new("test_table");
+        query
+            .offset(1)
+            .limit(2)
+            .add_order_by("count", Direction::Descending)
+            .having("COUNT(*) > 0")
+            .group_by(&["name"])
+            .where_not_null("optional")
+            .r#where("id >= 0")
+            .select_exprs(&["name", "COUNT(*) AS count"]);
+
+        assert_eq!(
+            query.build_sql_string(&query.selected_columns(&[])),
+            concat!(
+                r#"SELECT name, COUNT(*) AS count FROM test_table WHERE (id >= 0) AND ("optional" IS NOT NULL) "#,
+                r#"GROUP BY name HAVING COUNT(*) > 0 ORDER BY "count" DESC LIMIT 2 OFFSET 1 "#
+            )
+        );
+        assert_eq!(
+            query.build_paged_sql_string(&["name"], &[]),
+            concat!(
+                r#"SELECT name FROM test_table WHERE (id >= 0) AND ("optional" IS NOT NULL) "#,
+                r#"GROUP BY name HAVING COUNT(*) > 0 ORDER BY "count" DESC LIMIT ? OFFSET ? "#
+            )
+        );
+        assert_eq!(
+            query.build_keyset_sql_string(&["name"], "name", 5, false, &[]),
+            concat!(
+                r#"SELECT name FROM test_table WHERE (id >= 0) AND ("optional" IS NOT NULL) AND ("name" > ?) "#,
+                r#"GROUP BY name HAVING COUNT(*) > 0 ORDER BY "name" LIMIT 5 "#
+            )
+        );
+    }
+
+    #[test]
+    fn select_by_name_in_any_order() -> SealionResult<()> {
+        let connection = setup_test_db()?;
+
+        let rows: Vec<NamedRow> = SelectQuery::new("test_table")
+            .r#where("id = 2")
+            .execute_by_name(&connection, &["optional", "name", "id"])?;
+        assert_eq!(rows, vec![NamedRow { id: 2, name: "Peach".to_string() }]);
+
+        let result = SelectQuery::new("test_table").execute_by_name::<NamedRow>(&connection, &["optional", "name"]);
+        assert!(matches!(result, Err(SealionError::MissingColumns(columns)) if columns == vec!["id".to_string()]));
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_page_with_total() -> SealionResult<()> {
+        let connection = setup_test_db()?;
+
+        let page: Page<TestRow> = SelectQuery::new("test_table")
+            .filter("id > 0")
+            .order_by("id")
+            .execute_paged(&connection, 1, 1)?;
+        assert_eq!(page, Page {
+            rows: vec![TestRow { id: 2, name: "Peach".to_string(), optional: Some("Raspberry".to_string()) }],
+            total: 2,
+            page: 1,
+            size: 1
+        });
+
+        connection.execute("INSERT INTO test_table (id, name) VALUES (3, 'Apple')", [])?;
+        let mut grouped = SelectQuery::new("test_table");
+        grouped.select_exprs(&["MIN(id) AS id", "name"]).group_by(&["name"]).having("COUNT(*) > 1");
+        let page: Page<NamedRow> = grouped.execute_paged(&connection, 0, 10)?;
+        assert_eq!(page.rows, vec![NamedRow { id: 1, name: "Apple".to_string() }]);
+        assert_eq!(page.total, 1);
+
+        let result = SelectQuery::new("test_table").execute_paged::<TestRow>(&connection, u64::MAX, 2);
+        assert!(matches!(result, Err(SealionError::InvalidQuery(_))));
+
+        Ok(())
+    }
+
+    #[test]
+    fn quote_styles() {
+        assert_eq!(QuoteStyle::default(), QuoteStyle::DoubleQuote);
+        assert_eq!(QuoteStyle::DoubleQuote.quote("my \"col\""), "\"my \"\"col\"\"\"");
+        assert_eq!(QuoteStyle::Backtick.quote("my `col`"), "`my ``col```");
+        assert_eq!(QuoteStyle::Brackets.quote("my [col]"), "[my [col]]]");
+    }
+
+    #[test]
+    fn select_into_collections() -> SealionResult<()> {
+        let connection = setup_test_db()?;
+        let query = SelectQuery::new("test_table");
+
+        let rows: Box<[TestRow]> = query.execute_into(&connection)?;
+        assert_eq!(rows.len(), 3);
+
+        let rows: Vec<Arc<TestRow>> = query.execute_arc(&connection)?;
+        assert_eq!(*rows[1], TestRow { id: 1, name: "Apple".to_string(), optional: None });
+
+        let rows: Vec<Rc<TestRow>> = query.execute_rc(&connection)?;
+        assert_eq!(rows.len(), 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_distinct_set() -> SealionResult<()> {
+        #[derive(Debug, PartialEq, Eq, Hash)]
+        struct Name(String);
+
+        impl Row for Name {
+            fn columns<'a>() -> &'a[&'a str] {
+                &["name"]
+            }
+
+            fn parse_row(row: &rusqlite::Row) -> rusqlite::Result<Self> {
+                Ok(Self(row.get(0)?))
+            }
+        }
+
+        let connection = setup_test_db()?;
+        connection.execute("INSERT INTO test_table (id, name) VALUES (3, 'Apple')", [])?;
+
+        let names: HashSet<Name> = SelectQuery::new("test_table").execute_set(&connection)?;
+        assert_eq!(names.len(), 3);
+        assert!(names.contains(&Name("Apple".to_string())));
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_with_primary_keys() -> SealionResult<()> {
+        let connection = setup_test_db()?;
+
+        let rows: Vec<(u64, TestRow)> = SelectQuery::new("test_table").r#where("id > 0").execute_with_keys(&connection)?;
+        assert_eq!(rows, vec![
+            (1, TestRow { id: 1, name: "Apple".to_string(), optional: None }),
+            (2, TestRow { id: 2, name: "Peach".to_string(), optional: Some("Raspberry".to_string()) })
+        ]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn execute_discarding_rows() -> SealionResult<()> {
+        let connection = setup_test_db()?;
+
+        SelectQuery::new("test_table").r#where("id > 0").execute_discard(&connection)?;
+        SelectQuery::new("test_table").select_exprs(&["COUNT(*)"]).execute_discard(&connection)?;
+
+        // Errors from stepping through the rows are still reported
+        let result = SelectQuery::new("test_table").select_exprs(&["json('not json')"]).execute_discard(&connection);
+        assert!(result.is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_with_custom_mapper() -> SealionResult<()> {
+        let connection = setup_test_db()?;
+
+        let mut statement = connection.prepare("SELECT id, name FROM test_table ORDER BY id")?;
+        let rows = TestRow::from_statement_with(&mut statement, [], |row| Ok(TestRow {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            optional: None
+        }))?.collect::<rusqlite::Result<Vec<_>>>()?;
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[1], TestRow { id: 1, name: "Apple".to_string(), optional: None });
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_grouped_aggregate() -> SealionResult<()> {
+        #[derive(Debug, PartialEq, Eq)]
+        struct CategoryCount {
+            name: String,
+            count: i64
+        }
+
+        impl Row for CategoryCount {
+            fn columns<'a>() -> &'a[&'a str] {
+                &["name", "count"]
+            }
+
+            fn parse_row(row: &rusqlite::Row) -> rusqlite::Result<Self> {
+                Ok(Self {
+                    name: row.get(0)?,
+                    count: row.get(1)?
+                })
+            }
+        }
+
+        let connection = setup_test_db()?;
+        connection.execute(r#"INSERT INTO test_table (id, name, optional) VALUES (3, "Apple", "Cherry")"#, [])?;
+
+        let rows: Vec<CategoryCount> = SelectQuery::new("test_table")
+            .select_exprs(&["name", "COUNT(*) AS count"])
+            .group_by(&["name"])
+            .having("COUNT(*) > 1")
+            .execute(&connection)?;
+        assert_eq!(rows, vec![CategoryCount { name: "Apple".to_string(), count: 2 }]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn having_conditions_bind_after_where() -> SealionResult<()> {
+        #[derive(Row, Debug, PartialEq)]
+        struct NameCount {
+            name: String,
+            count: u64
+        }
+
+        let connection = setup_test_db()?;
+        connection.execute(r#"INSERT INTO test_table (id, name, optional) VALUES (3, "Apple", "Cherry"), (4, "Peach", NULL)"#, [])?;
+
+        let mut query = SelectQuery::new("test_table");
+        query
+            .select_exprs(&["name", "COUNT(*) AS count"])
+            .group_by(&["name"])
+            .and_having(Condition { sql: "COUNT(*) > ?".to_string(), params: vec![Box::new(1)] })
+            .and_having(Condition::new("name <> 'Peach'"))
+            .and_where(Condition::or(vec![Condition::eq("name", "Apple"), Condition::eq("name", "Peach")]));
+        assert_eq!(
+            query.build_sql_string(&query.selected_columns(&[])),
+            r#"SELECT name, COUNT(*) AS count FROM test_table WHERE ("name" = ?) OR ("name" = ?) GROUP BY name HAVING (COUNT(*) > ?) AND (name <> 'Peach') "#
+        );
+
+        let rows: Vec<NameCount> = query.execute(&connection)?;
+        assert_eq!(rows, vec![NameCount { name: "Apple".to_string(), count: 2 }]);
+        assert_eq!(query.count(&connection)?, 4);
+
+        // The count is bound to the same number as the id in the WHERE clause
+        query.conditions = vec![Condition { sql: r#""id" >= ?"#.to_string(), params: vec![Box::new(1)] }];
+        query.numbered_placeholders(true);
+        assert_eq!(
+            query.build_sql_string(&query.selected_columns(&[])),
+            r#"SELECT name, COUNT(*) AS count FROM test_table WHERE "id" >= ?1 GROUP BY name HAVING (COUNT(*) > ?1) AND (name <> 'Peach') "#
+        );
+        let rows: Vec<NameCount> = query.execute(&connection)?;
+        assert_eq!(rows, vec![NameCount { name: "Apple".to_string(), count: 2 }]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_correlated_subquery() -> SealionResult<()> {
+        #[derive(Row, Debug, PartialEq)]
+        struct BasketCount {
+            name: String,
+            baskets: u64
+        }
+
+        let connection = setup_test_db()?;
+        connection.execute_batch("
+            CREATE TABLE baskets (fruit_id INTEGER, quantity INTEGER);
+            INSERT INTO baskets VALUES (0, 1), (0, 5), (1, 8), (2, 2), (2, 9);
+        ")?;
+
+        let mut baskets = SelectQuery::new("baskets");
+        baskets
+            .select_exprs(&["COUNT(*)"])
+            .r#where("baskets.fruit_id = test_table.id")
+            .and_where(Condition::compare("quantity", Operator::Gt, Value::Integer(3)));
+
+        let mut query = SelectQuery::new("test_table");
+        query
+            .select_exprs(&["name"])
+            .select_subquery(baskets, "baskets")?
+            .and_where(Condition::compare("id", Operator::Le, Value::Integer(1)));
+        assert_eq!(query.select_exprs[1], r#"(SELECT COUNT(*) FROM baskets WHERE (baskets.fruit_id = test_table.id) AND ("quantity" > ?)) AS "baskets""#);
+
+        let rows: Vec<BasketCount> = query.execute(&connection)?;
+        assert_eq!(rows, vec![
+            BasketCount { name: "Orange".to_string(), baskets: 1 },
+            BasketCount { name: "Apple".to_string(), baskets: 1 }
+        ]);
+        assert_eq!(query.count(&connection)?, 2);
+
+        let mut unaliased = SelectQuery::new("baskets");
+        unaliased.select_exprs(&["COUNT(*)"]);
+        assert!(matches!(query.select_subquery(unaliased, ""), Err(SealionError::InvalidQuery(_))));
+        assert!(matches!(query.select_subquery(SelectQuery::new("baskets"), "baskets"), Err(SealionError::InvalidQuery(_))));
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_by_example() -> SealionResult<()> {
+        #[derive(Debug, Default)]
+        struct FruitSearch {
+            id: Option<u64>,
+            name: Option<String>
+        }
+
+        impl Row for FruitSearch {
+            fn columns<'a>() -> &'a[&'a str] {
+                &["id", "name"]
+            }
+
+            fn parse_row(row: &rusqlite::Row) -> rusqlite::Result<Self> {
+                Ok(Self {
+                    id: row.get(0)?,
+                    name: row.get(1)?
+                })
+            }
+        }
+
+        impl RowValues for FruitSearch {
+            fn to_params(&self) -> Vec<&dyn ToSql> {
+                vec![&self.id, &self.name]
+            }
+        }
+
+        impl Table for FruitSearch {
+            type Key = u64;
+
+            fn table_name<'a>() -> &'a str {
+                "test_table"
+            }
+        }
+
+        let connection = setup_test_db()?;
+
+        let query = SelectQuery::by_example(&FruitSearch { name: Some("Peach".to_string()), ..Default::default() })?;
+        assert_eq!(query.build_sql_string(&["id"]), r#"SELECT id FROM test_table WHERE "name" = ? "#);
+
+        let rows: Vec<TestRow> = query.execute(&connection)?;
+        assert_eq!(rows, vec![
+            TestRow { id: 2, name: "Peach".to_string(), optional: Some("Raspberry".to_string()) }
+        ]);
+
+        let rows: Vec<TestRow> = SelectQuery::by_example(&FruitSearch::default())?.execute(&connection)?;
+        assert_eq!(rows.len(), 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn count_rows() -> SealionResult<()> {
+        let connection = setup_test_db()?;
+        connection.execute(r#"INSERT INTO test_table (id, name, optional) VALUES (3, "Apple", "Cherry")"#, [])?;
+
+        assert_eq!(SelectQuery::new("test_table").count(&connection)?, 4);
+        assert_eq!(SelectQuery::new("test_table").where_null("optional").count(&connection)?, 1);
+
+        let counts: HashMap<String, u64> = SelectQuery::new("test_table").count_grouped(&connection, "name")?;
+        assert_eq!(counts, HashMap::from([
+            ("Apple".to_string(), 2),
+            ("Orange".to_string(), 1),
+            ("Peach".to_string(), 1)
+        ]));
+
+        let counts: HashMap<String, u64> = SelectQuery::new("test_table")
+            .where_not_null("optional")
+            .count_grouped(&connection, "name")?;
+        assert_eq!(counts.get("Apple"), Some(&1));
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_with_limit_and_offset() -> SealionResult<()> {
+        let connection = setup_test_db()?;
+
+        let rows = SelectQuery::new("test_table").offset(1).limit(1).execute::<TestRow>(&connection)?;
+        assert_eq!(rows, vec![
+            TestRow { id: 1, name: "Apple".to_string(), optional: None }
+        ]);
+
+        let rows: Vec<TestRow> = SelectQuery::new("test_table").offset(2).execute(&connection)?;
+        assert_eq!(rows, vec![
+            TestRow { id: 2, name: "Peach".to_string(), optional: Some("Raspberry".to_string()) }
+        ]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_in_chunks() -> SealionResult<()> {
+        let connection = setup_test_db()?;
+
+        let pages: Vec<Vec<TestRow>> = SelectQuery::new("test_table")
+            .chunks(&connection, 2)
+            .collect::<SealionResult<_>>()?;
+        assert_eq!(pages, vec![
+            vec![
+                TestRow { id: 0, name: "Orange".to_string(), optional: Some("Strawberry".to_string()) },
+                TestRow { id: 1, name: "Apple".to_string(), optional: None }
+            ],
+            vec![
+                TestRow { id: 2, name: "Peach".to_string(), optional: Some("Raspberry".to_string()) }
+            ]
+        ]);
+
+        let pages = SelectQuery::new("test_table").chunks::<TestRow>(&connection, 3).count();
+        assert_eq!(pages, 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_where_optional_value() -> SealionResult<()> {
+        let connection = setup_test_db()?;
+
+        let rows: Vec<TestRow> = SelectQuery::new("test_table")
+            .where_eq("optional", Some("Raspberry".to_string()))
+            .execute(&connection)?;
+        assert_eq!(rows, vec![
+            TestRow { id: 2, name: "Peach".to_string(), optional: Some("Raspberry".to_string()) }
+        ]);
+
+        // Binding NULL with = never matches
+        let rows: Vec<TestRow> = SelectQuery::new("test_table").where_eq("optional", None::<String>).execute(&connection)?;
+        assert!(rows.is_empty());
+
+        let rows: Vec<TestRow> = SelectQuery::new("test_table").where_opt_eq("optional", None::<String>).execute(&connection)?;
+        assert_eq!(rows, vec![
+            TestRow { id: 1, name: "Apple".to_string(), optional: None }
+        ]);
+
+        let rows: Vec<TestRow> = SelectQuery::new("test_table")
+            .where_opt_eq("optional", Some("Strawberry"))
+            .execute(&connection)?;
+        assert_eq!(rows, vec![
+            TestRow { id: 0, name: "Orange".to_string(), optional: Some("Strawberry".to_string()) }
+        ]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_where_is_null_safe() -> SealionResult<()> {
+        let connection = setup_test_db()?;
+        let apple = TestRow { id: 1, name: "Apple".to_string(), optional: None };
+
+        // = with a NULL parameter never matches, IS matches the NULL optional of Apple
+        assert_eq!(SelectQuery::new("test_table").where_eq("optional", None::<String>).count(&connection)?, 0);
+        let rows: Vec<TestRow> = SelectQuery::new("test_table").where_is("optional", None::<String>).execute(&connection)?;
+        assert_eq!(rows, vec![apple.clone()]);
+
+        // != leaves Apple out since its comparison is NULL, IS NOT includes it
+        assert_eq!(SelectQuery::new("test_table").r#where("optional != 'Raspberry'").count(&connection)?, 1);
+        let rows: Vec<TestRow> = SelectQuery::new("test_table").where_is_not("optional", "Raspberry").execute(&connection)?;
+        assert_eq!(rows.len(), 2);
+        assert!(rows.contains(&apple));
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_where_glob() -> SealionResult<()> {
+        let connection = setup_test_db()?;
+
+        let rows: Vec<TestRow> = SelectQuery::new("test_table").where_glob("name", "*ea*").execute(&connection)?;
+        assert_eq!(rows, vec![
+            TestRow { id: 2, name: "Peach".to_string(), optional: Some("Raspberry".to_string()) }
+        ]);
+
+        // GLOB is case-sensitive
+        assert_eq!(SelectQuery::new("test_table").where_glob("name", "p*").count(&connection)?, 0);
+        assert_eq!(SelectQuery::new("test_table").where_glob("name", "P*").count(&connection)?, 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_where_dynamic() -> SealionResult<()> {
+        let connection = setup_test_db()?;
+
+        let rows: Vec<TestRow> = SelectQuery::new("test_table")
+            .where_dynamic("id", ">=", Value::Integer(1))?
+            .where_dynamic("name", "like", Value::Text("a%".to_string()))?
+            .execute(&connection)?;
+        assert_eq!(rows, vec![
+            TestRow { id: 1, name: "Apple".to_string(), optional: None }
+        ]);
+
+        assert_eq!(SelectQuery::new("test_table").where_dynamic("optional", "=", Value::Null)?.count(&connection)?, 1);
+        assert_eq!(SelectQuery::new("test_table").where_dynamic("optional", "<>", Value::Null)?.count(&connection)?, 2);
+
+        let result = SelectQuery::new("test_table").where_dynamic("id", "; DROP TABLE test_table", Value::Integer(1)).map(|_| ());
+        assert!(matches!(result, Err(SealionError::UnknownOperator(_))));
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_where_in_subquery() -> SealionResult<()> {
+        let connection = setup_test_db()?;
+
+        let mut subquery = SelectQuery::new("test_table");
+        subquery.select_exprs(&["id"]).where_not_null("optional").where_glob("name", "P*");
+
+        let mut query = SelectQuery::new("test_table");
+        query.where_in_subquery("id", subquery)?;
+        assert_eq!(
+            query.build_sql_string(&["id"]),
+            r#"SELECT id FROM test_table WHERE "id" IN (SELECT id FROM test_table WHERE ("optional" IS NOT NULL) AND ("name" GLOB ?)) "#
+        );
+
+        let rows: Vec<TestRow> = query.execute(&connection)?;
+        assert_eq!(rows, vec![
+            TestRow { id: 2, name: "Peach".to_string(), optional: Some("Raspberry".to_string()) }
+        ]);
+
+        let result = SelectQuery::new("test_table").where_in_subquery("id", SelectQuery::new("test_table")).map(|_| ());
+        assert!(matches!(result, Err(SealionError::InvalidQuery(_))));
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_where_not() -> SealionResult<()> {
+        let connection = setup_test_db()?;
+
+        let condition = Condition::not(Condition::or(vec![
+            Condition::eq("name", "Orange"),
+            Condition::eq("name", "Peach")
+        ]));
+        assert_eq!(condition.sql, r#"NOT (("name" = ?) OR ("name" = ?))"#);
+
+        let rows: Vec<TestRow> = SelectQuery::new("test_table").and_where(condition).execute(&connection)?;
+        assert_eq!(rows, vec![TestRow { id: 1, name: "Apple".to_string(), optional: None }]);
+
+        let condition = Condition::and(vec![Condition::glob("name", "*e*"), !Condition::eq("id", 2)]);
+        let rows: Vec<TestRow> = SelectQuery::new("test_table").and_where(condition).execute(&connection)?;
+        assert_eq!(rows.iter().map(|row| row.id).collect::<Vec<_>>(), vec![0, 1]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn derived_row_round_trip() -> SealionResult<()> {
+        #[derive(Row, Debug, PartialEq, Eq)]
+        struct DerivedRow {
+            id: u64,
+            #[sealion(column = "name")]
+            fruit: String,
+            optional: Option<String>,
+            #[sealion(skip)]
+            note: String
+        }
+
+        assert_eq!(DerivedRow::columns(), &["id", "name", "optional"]);
+
+        let connection = setup_test_db()?;
+        let row = DerivedRow { id: 3, fruit: "Pear".to_string(), optional: None, note: "Not stored".to_string() };
+        InsertQuery::new("test_table").execute(&connection, &row)?;
+
+        let rows: Vec<DerivedRow> = SelectQuery::new("test_table").r#where("id >= 2").execute(&connection)?;
+        assert_eq!(rows, vec![
+            DerivedRow { id: 2, fruit: "Peach".to_string(), optional: Some("Raspberry".to_string()), note: String::new() },
+            DerivedRow { id: 3, fruit: "Pear".to_string(), optional: None, note: String::new() }
+        ]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn derived_row_rename_all() -> SealionResult<()> {
+        #[derive(Row, Debug, PartialEq, Eq)]
+        #[sealion(rename_all = "camelCase")]
+        struct CamelRow {
+            item_id: i64,
+            display_name: String,
+            #[sealion(column = "extra")]
+            something_else: Option<String>
+        }
+
+        assert_eq!(CamelRow::columns(), &["itemId", "displayName", "extra"]);
+
+        let connection = Connection::open_in_memory()?;
+        connection.execute_batch(r#"CREATE TABLE camel (itemId INTEGER, displayName TEXT, extra TEXT);
+            INSERT INTO camel VALUES (1, "One", NULL);"#)?;
+
+        let rows: Vec<CamelRow> = SelectQuery::new("camel").execute(&connection)?;
+        assert_eq!(rows, vec![CamelRow { item_id: 1, display_name: "One".to_string(), something_else: None }]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn derived_active_record() -> SealionResult<()> {
+        #[derive(Row, Debug, PartialEq, Eq)]
+        #[sealion(active_record)]
+        struct Fruit {
+            id: u64,
+            name: String,
+            optional: Option<String>
+        }
+
+        impl Table for Fruit {
+            type Key = u64;
+
+            fn table_name<'a>() -> &'a str {
+                "test_table"
+            }
+        }
+
+        let connection = setup_test_db()?;
+        let mut fruit = Fruit { id: 3, name: "Pear".to_string(), optional: None };
+        assert_eq!(fruit.insert(&connection)?, 1);
+
+        fruit.optional = Some("Lime".to_string());
+        assert_eq!(fruit.update(&connection)?, 1);
+        assert_eq!(Fruit::find_all(&connection)?.pop(), Some(Fruit { id: 3, name: "Pear".to_string(), optional: Some("Lime".to_string()) }));
+
+        assert_eq!(fruit.delete(&connection)?, 1);
+        assert_eq!(fruit.delete(&connection)?, 0);
+        assert_eq!(Fruit::find_all(&connection)?.len(), 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn derived_active_record_update_needs_primary_key() -> SealionResult<()> {
+        #[derive(Row, Debug, PartialEq, Eq)]
+        #[sealion(active_record)]
+        struct Fruit {
+            name: String,
+            optional: Option<String>
+        }
+
+        impl Table for Fruit {
+            type Key = u64;
+
+            fn table_name<'a>() -> &'a str {
+                "test_table"
+            }
+        }
+
+        let connection = setup_test_db()?;
+        let before: Vec<TestRow> = SelectQuery::new("test_table").execute(&connection)?;
+
+        let fruit = Fruit { name: "Pear".to_string(), optional: None };
+        assert!(matches!(fruit.update(&connection), Err(SealionError::UnknownColumns(_))));
+        assert_eq!(SelectQuery::new("test_table").execute::<TestRow>(&connection)?, before);
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_with_numbered_placeholders() -> SealionResult<()> {
+        let connection = setup_test_db()?;
+
+        let mut query = SelectQuery::new("test_table");
+        query
+            .numbered_placeholders(true)
+            .and_where(Condition::or(vec![Condition::eq("name", "Apple"), Condition::eq("optional", "Apple")]))
+            .and_where(Condition::new("name != '?'"))
+            .and_where(Condition::compare("id", Operator::Lt, Value::Integer(2)))
+            .and_where(Condition::compare("id", Operator::Ne, Value::Text("Apple".to_string())));
+        assert_eq!(
+            query.build_sql_string(&["id"]),
+            r#"SELECT id FROM test_table WHERE (("name" = ?1) OR ("optional" = ?1)) AND (name != '?') AND ("id" < ?2) AND ("id" != ?1) "#
+        );
+        assert_eq!(query.params(&[]).len(), 2);
+
+        let rows: Vec<TestRow> = query.execute(&connection)?;
+        assert_eq!(rows.iter().map(|row| row.id).collect::<Vec<_>>(), vec![1]);
+
+        let page: Page<TestRow> = query.execute_paged(&connection, 0, 10)?;
+        assert_eq!(page.total, 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_single_column() -> SealionResult<()> {
+        let connection = setup_test_db()?;
+
+        let names: Vec<String> = SelectQuery::new("test_table").execute_column(&connection, "name")?;
+        assert_eq!(names, vec!["Orange", "Apple", "Peach"]);
+
+        let optionals: Vec<String> = SelectQuery::new("test_table").execute_column_non_null(&connection, "optional")?;
+        assert_eq!(optionals, vec!["Strawberry", "Raspberry"]);
+
+        let result = SelectQuery::new("test_table").execute_column::<String>(&connection, "optional");
+        assert!(matches!(result, Err(SealionError::ColumnParse(_))));
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_where_all_pairs() -> SealionResult<()> {
+        let connection = setup_test_db()?;
+
+        let mut query = SelectQuery::new("test_table");
+        query.where_all(&[("name", &"Peach"), ("optional", &"Raspberry")])?;
+        assert_eq!(query.build_sql_string(&["id"]), r#"SELECT id FROM test_table WHERE "name" = ? AND "optional" = ? "#);
+
+        let rows: Vec<TestRow> = query.execute(&connection)?;
+        assert_eq!(rows.iter().map(|row| row.id).collect::<Vec<_>>(), vec![2]);
+
+        let mut query = SelectQuery::new("test_table");
+        query.where_all(&[])?;
+        assert_eq!(query.build_sql_string(&["id"]), "SELECT id FROM test_table ");
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_self_join_with_alias() -> SealionResult<()> {
+        let connection = setup_test_db()?;
+
+        let mut query = SelectQuery::new("test_table");
+        query
+            .alias("fruit")
+            .join("JOIN test_table AS next ON next.id = fruit.id + 1")
+            .select_exprs(&["fruit.id AS id", "next.name AS name"])
+            .r#where("fruit.optional IS NOT NULL");
+        assert_eq!(
+            query.build_sql_string(&query.selected_columns(&[])),
+            "SELECT fruit.id AS id, next.name AS name FROM test_table AS fruit JOIN test_table AS next ON next.id = fruit.id + 1 WHERE fruit.optional IS NOT NULL "
+        );
+
+        let rows: Vec<NamedRow> = query.execute(&connection)?;
+        assert_eq!(rows, vec![NamedRow { id: 0, name: "Apple".to_string() }]);
+        assert_eq!(query.count(&connection)?, 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_in_array() -> SealionResult<()> {
+        let connection = setup_test_db()?;
+
+        let mut query = SelectQuery::new("test_table");
+        query.where_in_array("id", vec![Value::Integer(0), Value::Integer(2)]);
+        assert_eq!(query.build_sql_string(&["id"]), r#"SELECT id FROM test_table WHERE "id" IN (?, ?) "#);
+
+        let rows: Vec<TestRow> = query.execute(&connection)?;
+        assert_eq!(rows.iter().map(|row| row.id).collect::<Vec<_>>(), vec![0, 2]);
+
+        let rows: Vec<TestRow> = SelectQuery::new("test_table").where_in_array("id", Vec::new()).execute(&connection)?;
+        assert!(rows.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_in_array_chunks_long_lists() -> SealionResult<()> {
+        let connection = setup_test_db()?;
+
+        let mut query = SelectQuery::new("test_table");
+        query.where_in_array("id", (0..=2000).filter(|id| id % 2 == 0).map(Value::Integer).collect());
+        assert_eq!(query.build_sql_string(&["id"]).matches(" IN (").count(), 2);
+
+        let rows: Vec<TestRow> = query.execute(&connection)?;
+        assert_eq!(rows.iter().map(|row| row.id).collect::<Vec<_>>(), vec![0, 2]);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "array")]
+    #[test]
+    fn select_in_rarray() -> SealionResult<()> {
+        let connection = setup_test_db()?;
+        rusqlite::vtab::array::load_module(&connection)?;
+
+        let mut query = SelectQuery::new("test_table");
+        query.where_in_rarray("id", (0..=1000).filter(|id| id % 2 == 0).map(Value::Integer).collect());
+        assert_eq!(query.build_sql_string(&["id"]), r#"SELECT id FROM test_table WHERE "id" IN rarray(?) "#);
+
+        let rows: Vec<TestRow> = query.execute(&connection)?;
+        assert_eq!(rows.iter().map(|row| row.id).collect::<Vec<_>>(), vec![0, 2]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_with_stable_order() -> SealionResult<()> {
+        let connection = setup_test_db()?;
+        connection.execute(r#"INSERT INTO test_table (id, name) VALUES (5, "Apple")"#, [])?;
+
+        let mut query = SelectQuery::new("test_table");
+        query.order_by("name").stable_order(true);
+        assert_eq!(query.build_sql_string(&["id"]), "SELECT id FROM test_table ORDER BY name, rowid ");
+
+        let rows: Vec<TestRow> = query.execute(&connection)?;
+        assert_eq!(rows.iter().map(|row| row.id).collect::<Vec<_>>(), vec![1, 5, 0, 2]);
+
+        let mut query = SelectQuery::new("test_table");
+        query.alias("fruit").stable_order(true);
+        assert_eq!(query.build_sql_string(&["id"]), "SELECT id FROM test_table AS fruit ORDER BY fruit.rowid ");
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_first_scalar() -> SealionResult<()> {
+        let connection = setup_test_db()?;
+
+        let mut query = SelectQuery::new("test_table");
+        query.select_exprs(&["name"]).r#where("id > ?").where_not_null("optional").order_by("id");
+        assert_eq!(query.scalar_first::<String>(&connection, &[&0])?, Some("Peach".to_string()));
+        assert_eq!(query.scalar_first::<String>(&connection, &[&2])?, None);
+
+        let mut query = SelectQuery::new("test_table");
+        query.select_exprs(&["MAX(id)"]).where_eq("name", "Apple");
+        assert_eq!(query.scalar_first::<u64>(&connection, &[])?, Some(1));
+
+        let mut subquery = SelectQuery::new("test_table");
+        subquery.select_exprs(&["COUNT(*)"]).where_eq("name", "Apple");
+        let mut query = SelectQuery::new("test_table");
+        query.select_subquery(subquery, "apples")?;
+        assert_eq!(query.scalar_first::<u64>(&connection, &[])?, Some(1));
+        query.select_exprs.push("id + ?".to_string());
+        assert!(matches!(query.scalar_first::<u64>(&connection, &[&1]), Err(SealionError::InvalidQuery(_))));
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_window_functions() -> SealionResult<()> {
+        #[derive(Row, Debug, PartialEq, Eq)]
+        struct Ranked {
+            name: String,
+            position: i64
+        }
+
+        let connection = setup_test_db()?;
+        let window = "ROW_NUMBER() OVER (PARTITION BY optional IS NULL ORDER BY name) AS position";
+
+        let mut query = SelectQuery::new("test_table");
+        query.select_exprs(&["name", window]).order_by("name");
+        assert_eq!(
+            query.build_sql_string(&query.selected_columns(&[])),
+            format!("SELECT name, {} FROM test_table ORDER BY name ", window)
+        );
+
+        let rows: Vec<Ranked> = query.execute(&connection)?;
+        assert_eq!(rows, vec![
+            Ranked { name: "Apple".to_string(), position: 1 },
+            Ranked { name: "Orange".to_string(), position: 1 },
+            Ranked { name: "Peach".to_string(), position: 2 }
+        ]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn user_messages_hide_details() -> SealionResult<()> {
+        let connection = setup_test_db()?;
+
+        let row = TestRow { id: 1, name: "Apple".to_string(), optional: None };
+        let err = InsertQuery::new("test_table").execute(&connection, &row).unwrap_err();
+        assert_eq!(err.user_message(), "Duplicate entry");
+        assert!(err.to_string().contains("test_table.id"));
+
+        let err = SelectQuery::new("missing_table").count(&connection).unwrap_err();
+        assert_eq!(err.user_message(), "An internal error occurred");
+        assert_eq!(SealionError::InvalidFilter("Expected a value at position 5".to_string()).user_message(), "The filter is invalid");
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_without_table() -> SealionResult<()> {
+        let connection = setup_test_db()?;
+
+        let mut query = SelectQuery::expression();
+        query.select_exprs(&["1 + 2 AS id", "'Kiwi' AS name"]);
+        assert_eq!(query.build_sql_string(&query.selected_columns(&[])), "SELECT 1 + 2 AS id, 'Kiwi' AS name ");
+
+        let rows: Vec<NamedRow> = query.execute(&connection)?;
+        assert_eq!(rows, vec![NamedRow { id: 3, name: "Kiwi".to_string() }]);
+        assert_eq!(query.scalar_first::<u64>(&connection, &[])?, Some(3));
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_with_conditional_steps() -> SealionResult<()> {
+        let connection = setup_test_db()?;
+
+        let run = |name: Option<&'static str>, only_optional: bool| -> SealionResult<Vec<u64>> {
+            let rows: Vec<TestRow> = SelectQuery::new("test_table")
+                .if_some(name, |query, name| { query.where_eq("name", name); })
+                .when(only_optional, |query| { query.where_not_null("optional"); })
+                .execute(&connection)?;
+            Ok(rows.iter().map(|row| row.id).collect())
+        };
+        assert_eq!(run(None, false)?, vec![0, 1, 2]);
+        assert_eq!(run(Some("Apple"), false)?, vec![1]);
+        assert_eq!(run(Some("Apple"), true)?, Vec::<u64>::new());
+        assert_eq!(run(None, true)?, vec![0, 2]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_rows_by_tag() -> SealionResult<()> {
+        #[derive(Debug, PartialEq, Eq)]
+        enum Fruit {
+            Berry { name: String },
+            Pit { id: u64 }
+        }
+
+        impl Row for Fruit {
+            fn columns<'a>() -> &'a [&'a str] {
+                &["kind", "id", "name"]
+            }
+
+            fn parse_row(row: &rusqlite::Row) -> rusqlite::Result<Self> {
+                parse_tagged(row, "kind", &[
+                    ("berry", |row| Ok(Fruit::Berry { name: get_named(row, "name")? })),
+                    ("pit", |row| Ok(Fruit::Pit { id: get_named(row, "id")? }))
+                ])
+            }
+        }
+
+        let connection = setup_test_db()?;
+        let mut query = SelectQuery::new("test_table");
+        query.select_exprs(&["CASE WHEN optional IS NULL THEN 'pit' ELSE 'berry' END AS kind", "id", "optional AS name"]);
+
+        let rows: Vec<Fruit> = query.execute(&connection)?;
+        assert_eq!(rows, vec![
+            Fruit::Berry { name: "Strawberry".to_string() },
+            Fruit::Pit { id: 1 },
+            Fruit::Berry { name: "Raspberry".to_string() }
+        ]);
+
+        query.select_exprs(&["'seed' AS kind", "id", "name"]);
+        let result = query.execute::<Fruit>(&connection);
+        assert!(matches!(result, Err(SealionError::ParseError { row: 0, source }) if matches!(*source, SealionError::ColumnParse(ref err) if err.column == "kind")));
 
-        let rows: Vec<TestRow> = SelectQuery::new("test_table")
-            .r#where("optional IS NOT NULL").execute(&connection)?;
-        assert_eq!(rows, vec![
-            TestRow { id: 0, name: "Orange".to_string(), optional: Some("Strawberry".to_string()) },
-            TestRow { id: 2, name: "Peach".to_string(), optional: Some("Raspberry".to_string()) }
-        ]);
         Ok(())
     }
 }
@@ -0,0 +1,91 @@
+use std::ops::Deref;
+use rusqlite::Connection;
+
+use crate::cache::prepare_cached;
+use crate::SealionResult;
+
+/// The outcome of `integrity_check`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Integrity {
+    /// No problems were found.
+    Ok,
+    /// The problems found, one per line of the `PRAGMA integrity_check` output.
+    Problems(Vec<String>)
+}
+
+impl Integrity {
+    pub fn is_ok(&self) -> bool {
+        matches!(self, Integrity::Ok)
+    }
+}
+
+/// Checks the database for corruption with `PRAGMA integrity_check`.
+///
+/// This reads the entire database, so it can take a while on large databases.
+pub fn integrity_check(connection: impl Deref<Target = Connection>) -> SealionResult<Integrity> {
+    let mut statement = prepare_cached(&connection, "PRAGMA integrity_check")?;
+    let lines = statement.query_map([], |row| row.get::<_, String>(0))?
+        .collect::<rusqlite::Result<Vec<String>>>()?;
+
+    if lines == ["ok"] {
+        Ok(Integrity::Ok)
+    } else {
+        Ok(Integrity::Problems(lines))
+    }
+}
+
+/// Returns the number of rows changed by the most recent INSERT, UPDATE or DELETE on the connection.
+///
+/// Like the counts returned by the mutation builders, this leaves out rows changed by triggers.
+pub fn changes(connection: impl Deref<Target = Connection>) -> SealionResult<u64> {
+    let mut statement = prepare_cached(&connection, "SELECT changes()")?;
+    Ok(statement.query_row([], |row| row.get(0))?)
+}
+
+/// Returns the number of rows changed by every INSERT, UPDATE and DELETE since the connection was opened,
+/// including rows changed by triggers.
+///
+/// Comparing this before and after a statement shows how many rows it changed in total, which helps
+/// diagnose triggers that change more than expected.
+pub fn total_changes(connection: impl Deref<Target = Connection>) -> SealionResult<u64> {
+    let mut statement = prepare_cached(&connection, "SELECT total_changes()")?;
+    Ok(statement.query_row([], |row| row.get(0))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{changes, integrity_check, total_changes, InsertQuery, Integrity, SealionResult};
+    use crate::tests::{setup_test_db, TestRow};
+
+    #[test]
+    fn healthy_database_is_ok() -> SealionResult<()> {
+        let connection = setup_test_db()?;
+
+        let integrity = integrity_check(&connection)?;
+        assert_eq!(integrity, Integrity::Ok);
+        assert!(integrity.is_ok());
+
+        Ok(())
+    }
+
+    #[test]
+    fn changes_include_triggers_in_total() -> SealionResult<()> {
+        let connection = setup_test_db()?;
+        connection.execute_batch("
+            CREATE TABLE audit (fruit_id INTEGER);
+            CREATE TRIGGER audit_insert AFTER INSERT ON test_table BEGIN
+                INSERT INTO audit VALUES (new.id);
+                INSERT INTO audit VALUES (new.id);
+            END;
+        ")?;
+
+        let before = total_changes(&connection)?;
+        let row = TestRow { id: 3, name: "Pear".to_string(), optional: None };
+        assert_eq!(InsertQuery::new("test_table").execute(&connection, &row)?, 1);
+
+        assert_eq!(changes(&connection)?, 1);
+        assert_eq!(total_changes(&connection)? - before, 3);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,70 @@
+use std::hash::Hash;
+use std::ops::Deref;
+use indexmap::IndexMap;
+use rusqlite::Connection;
+
+use crate::{SealionResult, SelectQuery, Table};
+
+impl SelectQuery {
+    /// Like `execute_with_keys`, but collects the rows into an `IndexMap` keyed by primary key, which keeps the
+    /// order the rows were returned in while allowing them to be looked up by key.
+    ///
+    /// If several rows have the same key, the last one wins but keeps the position of the first. Like
+    /// `execute_with_keys`, this fails with `SealionError::InvalidQuery` for a composite primary key.
+    pub fn execute_into_ordered<R: Table>(&self, connection: impl Deref<Target = Connection>) -> SealionResult<IndexMap<R::Key, R>>
+    where
+        R::Key: Hash + Eq
+    {
+        Ok(self.execute_with_keys(connection)?.into_iter().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rusqlite::Connection;
+
+    use crate::{Row, SealionError, SealionResult, SelectQuery, Table};
+    use crate::tests::{setup_test_db, TestRow};
+
+    #[test]
+    fn rows_are_keyed_in_order() -> SealionResult<()> {
+        let connection = setup_test_db()?;
+
+        let rows = SelectQuery::new("test_table").order_by("name").execute_into_ordered::<TestRow>(&connection)?;
+        assert_eq!(rows.keys().copied().collect::<Vec<_>>(), vec![1, 0, 2]);
+        assert_eq!(rows[&2].name, "Peach");
+        assert_eq!(rows.get_index_of(&0), Some(1));
+
+        Ok(())
+    }
+
+    #[test]
+    fn composite_keys_are_rejected() -> SealionResult<()> {
+        #[derive(Row, Debug)]
+        struct Membership {
+            user_id: i64,
+            group_id: i64
+        }
+
+        impl Table for Membership {
+            type Key = i64;
+
+            fn table_name<'a>() -> &'a str {
+                "memberships"
+            }
+
+            fn primary_key<'a>() -> &'a [&'a str] {
+                &["user_id", "group_id"]
+            }
+        }
+
+        let connection = Connection::open_in_memory()?;
+        connection.execute_batch("CREATE TABLE memberships (user_id INTEGER, group_id INTEGER, PRIMARY KEY (user_id, group_id));
+            INSERT INTO memberships VALUES (1, 1), (1, 2);")?;
+
+        let result = Membership::select().execute_into_ordered::<Membership>(&connection);
+        assert!(matches!(result, Err(SealionError::InvalidQuery(_))));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,114 @@
+use std::ops::Deref;
+use rusqlite::{Connection, ToSql, params_from_iter};
+
+use crate::cache::prepare_cached;
+use crate::{check_columns, Row, SealionResult, SelectQuery};
+
+/// A row that can be paged through by one of its columns with a `KeysetPager`.
+pub trait KeysetRow: Row {
+    /// The type of the column rows are paged by.
+    type Cursor: ToSql;
+
+    /// Returns the value of the column rows are paged by, which the next page starts after.
+    fn cursor(&self) -> Self::Cursor;
+}
+
+/// Pages through the rows of a query using keyset pagination, keeping track of the cursor between pages.
+///
+/// This packages up `SelectQuery::after`: rows are ordered by `column`, and each page starts after the
+/// cursor of the last row of the previous page, read with `KeysetRow::cursor`. The values of `column`
+/// should be unique, or rows sharing a value across a page boundary will be skipped.
+pub struct KeysetPager<R: KeysetRow> {
+    query: SelectQuery,
+    column: String,
+    page_size: u64,
+    cursor: Option<R::Cursor>,
+    exhausted: bool
+}
+
+impl<R: KeysetRow> KeysetPager<R> {
+    pub fn new<S: ToString>(query: SelectQuery, column: S, page_size: u64) -> Self {
+        Self {
+            query,
+            column: column.to_string(),
+            page_size,
+            cursor: None,
+            exhausted: page_size == 0
+        }
+    }
+
+    /// Fetches the next page of rows. Once a page has fewer than `page_size` rows the pager is exhausted,
+    /// and every following page is empty without running the query.
+    pub fn next_page(&mut self, connection: impl Deref<Target = Connection>) -> SealionResult<Vec<R>> {
+        if self.exhausted {
+            return Ok(Vec::new());
+        }
+
+        let hooked = self.query.hook_conditions();
+        let first_page = self.cursor.is_none();
+        let sql_string = self.query.build_keyset_sql_string(&self.query.selected_columns(R::columns()), &self.column, self.page_size, first_page, &hooked);
+        let mut statement = prepare_cached(&connection, &sql_string)?;
+        check_columns(&statement, R::columns());
+
+        let params = self.query.params_with_cursor(&hooked, self.cursor.as_ref().map(|cursor| cursor as &dyn ToSql));
+        let rows = R::from_statement(&mut statement, params_from_iter(params))?
+            .collect::<rusqlite::Result<Vec<R>>>()?;
+
+        if (rows.len() as u64) < self.page_size {
+            self.exhausted = true;
+        }
+        if let Some(last) = rows.last() {
+            self.cursor = Some(last.cursor());
+        }
+        Ok(rows)
+    }
+
+    /// Returns true once every page has been fetched.
+    pub fn is_exhausted(&self) -> bool {
+        self.exhausted
+    }
+
+    /// Returns the cursor the next page starts after, or `None` before the first page.
+    pub fn cursor(&self) -> Option<&R::Cursor> {
+        self.cursor.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{KeysetPager, KeysetRow, SealionResult, SelectQuery};
+    use crate::tests::{setup_test_db, TestRow};
+
+    impl KeysetRow for TestRow {
+        type Cursor = u64;
+
+        fn cursor(&self) -> Self::Cursor {
+            self.id
+        }
+    }
+
+    #[test]
+    fn page_through_rows() -> SealionResult<()> {
+        let connection = setup_test_db()?;
+
+        let mut pager: KeysetPager<TestRow> = KeysetPager::new(SelectQuery::new("test_table"), "id", 2);
+        let first: Vec<u64> = pager.next_page(&connection)?.iter().map(|row| row.id).collect();
+        assert_eq!(first, vec![0, 1]);
+        assert_eq!(pager.cursor(), Some(&1));
+        assert!(!pager.is_exhausted());
+
+        let second: Vec<u64> = pager.next_page(&connection)?.iter().map(|row| row.id).collect();
+        assert_eq!(second, vec![2]);
+        assert!(pager.is_exhausted());
+        assert!(pager.next_page(&connection)?.is_empty());
+
+        let mut query = SelectQuery::new("test_table");
+        query.where_not_null("optional");
+        let mut pager: KeysetPager<TestRow> = KeysetPager::new(query, "id", 2);
+        assert_eq!(pager.next_page(&connection)?.len(), 2);
+        assert!(pager.next_page(&connection)?.is_empty());
+        assert!(pager.is_exhausted());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,141 @@
+use std::ops::Deref;
+use rusqlite::Connection;
+
+use crate::cache::prepare_cached;
+use crate::{check_columns, Row, RowParser, SealionError, SealionResult};
+
+/// Runs `PRAGMA pragma(arg)` and parses the rows it returns into `R`.
+///
+/// The columns of `R` are checked against the columns of the pragma like any other query. `ColumnInfo`,
+/// `ForeignKeyInfo` and `IndexInfo` match the `table_info`, `foreign_key_list` and `index_list` pragmas.
+///
+/// Pragmas can't take bound parameters, so `arg` is embedded as a quoted string literal. Fails with
+/// `SealionError::InvalidQuery` if `pragma` isn't a plain, optionally schema qualified, name.
+///
+/// ```
+/// # use sealion::{pragma, ColumnInfo};
+/// let connection = rusqlite::Connection::open_in_memory()?;
+/// connection.execute("CREATE TABLE fruits (id INTEGER PRIMARY KEY, name TEXT NOT NULL)", [])?;
+///
+/// let columns: Vec<ColumnInfo> = pragma(&connection, "table_info", Some("fruits"))?;
+/// assert_eq!(columns[1].name, "name");
+/// assert!(columns[1].not_null);
+/// # Ok::<(), sealion::SealionError>(())
+/// ```
+pub fn pragma<R: Row>(connection: impl Deref<Target = Connection>, pragma: &str, arg: Option<&str>) -> SealionResult<Vec<R>> {
+    let is_name = |part: &str| !part.is_empty() && part.chars().all(|c| c == '_' || c.is_ascii_alphanumeric());
+    if !pragma.split('.').all(is_name) || pragma.split('.').count() > 2 {
+        return Err(SealionError::InvalidQuery(format!("{} is not a pragma name", pragma)));
+    }
+
+    let sql_string = match arg {
+        Some(arg) => format!("PRAGMA {}('{}')", pragma, arg.replace('\'', "''")),
+        None => format!("PRAGMA {}", pragma)
+    };
+
+    let mut statement = prepare_cached(&connection, &sql_string)?;
+    check_columns(&statement, R::columns());
+
+    let rows = statement.query_map([], R::parse_row as RowParser<R>)?
+        .collect::<rusqlite::Result<Vec<R>>>()?;
+    Ok(rows)
+}
+
+/// A column of a table, as returned by `PRAGMA table_info`.
+#[derive(Row, Clone, Debug, PartialEq, Eq)]
+pub struct ColumnInfo {
+    /// The position of the column in the table, starting from 0.
+    pub cid: i64,
+    pub name: String,
+    /// The declared type of the column, which is empty if it has none.
+    #[sealion(column = "type")]
+    pub sql_type: String,
+    #[sealion(column = "notnull")]
+    pub not_null: bool,
+    /// The default value of the column as an SQL expression.
+    #[sealion(column = "dflt_value")]
+    pub default: Option<String>,
+    /// The position of the column in the primary key starting from 1, or 0 if it isn't part of it.
+    pub pk: i64
+}
+
+/// A column of a foreign key, as returned by `PRAGMA foreign_key_list`.
+#[derive(Row, Clone, Debug, PartialEq, Eq)]
+pub struct ForeignKeyInfo {
+    /// Identifies the foreign key. The columns of a foreign key over several columns share the same id.
+    pub id: i64,
+    /// The position of the column in the foreign key, starting from 0.
+    pub seq: i64,
+    /// The referenced table.
+    pub table: String,
+    #[sealion(column = "from")]
+    pub from_column: String,
+    /// The referenced column, or `None` if the foreign key refers to the primary key implicitly.
+    #[sealion(column = "to")]
+    pub to_column: Option<String>,
+    pub on_update: String,
+    pub on_delete: String,
+    #[sealion(column = "match")]
+    pub match_clause: String
+}
+
+/// An index of a table, as returned by `PRAGMA index_list`.
+#[derive(Row, Clone, Debug, PartialEq, Eq)]
+pub struct IndexInfo {
+    pub seq: i64,
+    pub name: String,
+    pub unique: bool,
+    /// How the index was created: `c` for CREATE INDEX, `u` for a UNIQUE constraint and `pk` for a
+    /// PRIMARY KEY constraint.
+    pub origin: String,
+    pub partial: bool
+}
+
+#[cfg(test)]
+mod tests {
+    use rusqlite::Connection;
+
+    use crate::{pragma, ColumnInfo, ForeignKeyInfo, IndexInfo, SealionError, SealionResult};
+
+    #[test]
+    fn read_common_pragmas() -> SealionResult<()> {
+        let connection = Connection::open_in_memory()?;
+        connection.execute_batch("
+            CREATE TABLE fruits (id INTEGER PRIMARY KEY, name TEXT NOT NULL UNIQUE, colour TEXT DEFAULT 'green');
+            CREATE TABLE baskets (id INTEGER PRIMARY KEY, fruit_id INTEGER REFERENCES fruits (id) ON DELETE CASCADE);
+            CREATE INDEX fruits_colour ON fruits (colour) WHERE colour IS NOT NULL;
+        ")?;
+
+        let columns: Vec<ColumnInfo> = pragma(&connection, "table_info", Some("fruits"))?;
+        assert_eq!(columns[2], ColumnInfo {
+            cid: 2,
+            name: "colour".to_string(),
+            sql_type: "TEXT".to_string(),
+            not_null: false,
+            default: Some("'green'".to_string()),
+            pk: 0
+        });
+        assert_eq!(columns.iter().map(|column| column.pk).collect::<Vec<_>>(), vec![1, 0, 0]);
+
+        let foreign_keys: Vec<ForeignKeyInfo> = pragma(&connection, "main.foreign_key_list", Some("baskets"))?;
+        assert_eq!(foreign_keys.len(), 1);
+        assert_eq!(foreign_keys[0].table, "fruits");
+        assert_eq!(foreign_keys[0].from_column, "fruit_id");
+        assert_eq!(foreign_keys[0].to_column.as_deref(), Some("id"));
+        assert_eq!(foreign_keys[0].on_delete, "CASCADE");
+
+        let mut indexes: Vec<IndexInfo> = pragma(&connection, "index_list", Some("fruits"))?;
+        indexes.sort_by(|a, b| a.name.cmp(&b.name));
+        assert_eq!(indexes.iter().map(|index| (index.origin.as_str(), index.unique, index.partial)).collect::<Vec<_>>(), vec![
+            ("c", false, true),
+            ("u", true, false)
+        ]);
+
+        // The argument is quoted, not spliced into the statement
+        let columns: Vec<ColumnInfo> = pragma(&connection, "table_info", Some("fruits'); DROP TABLE fruits; --"))?;
+        assert!(columns.is_empty());
+        assert!(matches!(pragma::<ColumnInfo>(&connection, "table_info; DROP TABLE fruits", None), Err(SealionError::InvalidQuery(_))));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,67 @@
+use std::collections::HashMap;
+use std::ops::Deref;
+use rusqlite::{CachedStatement, Connection};
+use rusqlite::types::Value;
+
+use crate::{Row, SealionError, SealionResult, SelectQuery};
+
+/// A catalog of named select queries.
+///
+/// Registering every query an application runs in one place keeps SQL building out of the call sites,
+/// which only need to refer to queries by name. Statements are prepared through rusqlite's statement
+/// cache, so looking up the same query repeatedly doesn't re-prepare it.
+#[derive(Default)]
+pub struct QueryRegistry {
+    queries: HashMap<String, SelectQuery>
+}
+
+impl QueryRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers `query` under `name`, replacing any query previously registered with that name.
+    pub fn register<S: ToString>(&mut self, name: S, query: SelectQuery) -> &mut Self {
+        self.queries.insert(name.to_string(), query);
+        self
+    }
+
+    pub fn get(&self, name: &str) -> SealionResult<&SelectQuery> {
+        self.queries.get(name).ok_or_else(|| SealionError::UnknownQuery(name.to_string()))
+    }
+
+    /// Prepares the query registered under `name`, along with the parameters to bind to it. See
+    /// `SelectQuery::prepare_statement_columns`.
+    pub fn prepare_statement<'conn, R: Row>(&self, connection: &'conn Connection, name: &str) -> SealionResult<(CachedStatement<'conn>, Vec<Value>)> {
+        self.get(name)?.prepare_statement::<R>(connection)
+    }
+
+    pub fn execute<R: Row>(&self, connection: impl Deref<Target = Connection>, name: &str) -> SealionResult<Vec<R>> {
+        self.get(name)?.execute(connection)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{QueryRegistry, SealionError, SealionResult, SelectQuery};
+    use crate::tests::{setup_test_db, TestRow};
+
+    #[test]
+    fn execute_registered_query() -> SealionResult<()> {
+        let connection = setup_test_db()?;
+
+        let mut registry = QueryRegistry::new();
+        let mut without_optional = SelectQuery::new("test_table");
+        without_optional.r#where("optional IS NULL");
+        registry.register("without_optional", without_optional);
+
+        let rows: Vec<TestRow> = registry.execute(&connection, "without_optional")?;
+        assert_eq!(rows, vec![
+            TestRow { id: 1, name: "Apple".to_string(), optional: None }
+        ]);
+
+        assert!(matches!(registry.execute::<TestRow>(&connection, "missing"), Err(SealionError::UnknownQuery(name)) if name == "missing"));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,91 @@
+use std::ops::Deref;
+use rusqlite::{Connection, OptionalExtension, params};
+
+use crate::cache::prepare_cached;
+use crate::{SealionError, SealionResult, Table};
+
+/// The table the schema version of every `Table` is stored in.
+pub const SCHEMA_VERSIONS_TABLE: &str = "sealion_schema_versions";
+
+/// Checks that the schema version stored for the table of `R` is `R::SCHEMA_VERSION`.
+///
+/// Running this at startup catches code that expects a different schema than the database has. Fails with
+/// `SealionError::SchemaVersion` if the versions differ or if no version has been stored, which means the
+/// table needs to be migrated and the new version recorded with `set_schema_version`.
+pub fn check_schema_version<R: Table>(connection: impl Deref<Target = Connection>) -> SealionResult<()> {
+    let found = schema_version(&connection, R::table_name())?;
+    if found != Some(R::SCHEMA_VERSION) {
+        return Err(SealionError::SchemaVersion {
+            table: R::table_name().to_string(),
+            expected: R::SCHEMA_VERSION,
+            found
+        });
+    }
+    Ok(())
+}
+
+/// Records `R::SCHEMA_VERSION` as the schema version of the table of `R`, creating the `SCHEMA_VERSIONS_TABLE`
+/// if needed. Call this once the table has been created or migrated, ideally in the same transaction.
+pub fn set_schema_version<R: Table>(connection: impl Deref<Target = Connection>) -> SealionResult<()> {
+    connection.execute(&format!("CREATE TABLE IF NOT EXISTS {} (table_name TEXT PRIMARY KEY, version INTEGER NOT NULL)", SCHEMA_VERSIONS_TABLE), [])?;
+
+    let mut statement = prepare_cached(&connection, &format!("INSERT OR REPLACE INTO {} (table_name, version) VALUES (?, ?)", SCHEMA_VERSIONS_TABLE))?;
+    statement.execute(params![R::table_name(), R::SCHEMA_VERSION])?;
+    Ok(())
+}
+
+/// Returns the schema version stored for `table_name`, or `None` if there isn't one.
+fn schema_version(connection: &Connection, table_name: &str) -> SealionResult<Option<u32>> {
+    let has_versions: bool = prepare_cached(connection, "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?)")?
+        .query_row([SCHEMA_VERSIONS_TABLE], |row| row.get(0))?;
+    if !has_versions {
+        return Ok(None);
+    }
+
+    let mut statement = prepare_cached(connection, &format!("SELECT version FROM {} WHERE table_name = ?", SCHEMA_VERSIONS_TABLE))?;
+    Ok(statement.query_row([table_name], |row| row.get(0)).optional()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{check_schema_version, set_schema_version, Row, SealionError, SealionResult, Table};
+    use crate::tests::{setup_test_db, TestRow};
+
+    #[derive(Row)]
+    #[allow(dead_code)]
+    struct TestRowV2 {
+        id: u64,
+        name: String,
+        optional: Option<String>
+    }
+
+    impl Table for TestRowV2 {
+        type Key = u64;
+
+        const SCHEMA_VERSION: u32 = 2;
+
+        fn table_name<'a>() -> &'a str {
+            "test_table"
+        }
+    }
+
+    #[test]
+    fn schema_version_mismatch() -> SealionResult<()> {
+        let connection = setup_test_db()?;
+        let result = check_schema_version::<TestRow>(&connection);
+        assert!(matches!(result, Err(SealionError::SchemaVersion { expected: 1, found: None, .. })));
+
+        set_schema_version::<TestRow>(&connection)?;
+        check_schema_version::<TestRow>(&connection)?;
+
+        let err = check_schema_version::<TestRowV2>(&connection).unwrap_err();
+        assert!(matches!(err, SealionError::SchemaVersion { expected: 2, found: Some(1), .. }));
+        assert_eq!(err.to_string(), "Table test_table is at schema version 1, expected version 2");
+
+        set_schema_version::<TestRowV2>(&connection)?;
+        check_schema_version::<TestRowV2>(&connection)?;
+        assert!(check_schema_version::<TestRow>(&connection).is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,184 @@
+use std::fmt;
+use rusqlite::types::{Type, Value};
+use serde::de::{self, DeserializeOwned, DeserializeSeed, IntoDeserializer, MapAccess, Visitor};
+use serde::forward_to_deserialize_any;
+
+use crate::Row;
+
+/// A row parsed with serde, for reusing `Deserialize` types as rows.
+///
+/// Like `DynRow`, selecting `SerdeRow`s selects every column (`SELECT *`). Each column is then fed to
+/// `T` as a struct field of the same name. Only flat structs of primitive fields are supported:
+/// integers, floats, booleans (stored as integers), strings, byte buffers and options of those.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SerdeRow<T>(pub T);
+
+impl<T> SerdeRow<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T: DeserializeOwned> Row for SerdeRow<T> {
+    fn columns<'a>() -> &'a[&'a str] {
+        &["*"]
+    }
+
+    fn parse_row(row: &rusqlite::Row) -> rusqlite::Result<Self> {
+        let statement = row.as_ref();
+        let mut columns = Vec::with_capacity(statement.column_count());
+
+        for index in 0..statement.column_count() {
+            columns.push((statement.column_name(index)?, row.get::<_, Value>(index)?));
+        }
+
+        let mut deserializer = RowDeserializer { columns: &columns, index: 0 };
+        T::deserialize(&mut deserializer)
+            .map(SerdeRow)
+            .map_err(|err| {
+                let index = deserializer.index.saturating_sub(1);
+                let data_type = columns.get(index).map_or(Type::Null, |(_, value)| value.data_type());
+                rusqlite::Error::FromSqlConversionFailure(index, data_type, Box::new(err))
+            })
+    }
+}
+
+#[derive(Debug)]
+pub struct DeserializeError(String);
+
+impl fmt::Display for DeserializeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for DeserializeError {}
+
+impl de::Error for DeserializeError {
+    fn custom<M: fmt::Display>(msg: M) -> Self {
+        DeserializeError(msg.to_string())
+    }
+}
+
+/// Presents the columns of a row as a map of column name to value.
+struct RowDeserializer<'r> {
+    columns: &'r [(&'r str, Value)],
+    index: usize
+}
+
+impl<'de, 'r> de::Deserializer<'de> for &mut RowDeserializer<'r> {
+    type Error = DeserializeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_map(self)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+impl<'de, 'r> MapAccess<'de> for RowDeserializer<'r> {
+    type Error = DeserializeError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error> {
+        match self.columns.get(self.index) {
+            Some((name, _)) => seed.deserialize(name.to_string().into_deserializer()).map(Some),
+            None => Ok(None)
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Self::Error> {
+        let (_, value) = &self.columns[self.index];
+        self.index += 1;
+        seed.deserialize(ValueDeserializer(value))
+    }
+}
+
+/// Deserializes a single column value.
+struct ValueDeserializer<'r>(&'r Value);
+
+impl<'de, 'r> de::Deserializer<'de> for ValueDeserializer<'r> {
+    type Error = DeserializeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.0 {
+            Value::Null => visitor.visit_unit(),
+            Value::Integer(i) => visitor.visit_i64(*i),
+            Value::Real(f) => visitor.visit_f64(*f),
+            Value::Text(s) => visitor.visit_str(s),
+            Value::Blob(b) => visitor.visit_bytes(b)
+        }
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.0 {
+            Value::Integer(i) => visitor.visit_bool(*i != 0),
+            _ => self.deserialize_any(visitor)
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.0 {
+            Value::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    forward_to_deserialize_any! {
+        i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+        enum identifier ignored_any
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use crate::{SealionResult, SelectQuery, SerdeRow};
+    use crate::tests::setup_test_db;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Fruit {
+        id: u32,
+        name: String,
+        optional: Option<String>
+    }
+
+    #[test]
+    fn select_serde_rows() -> SealionResult<()> {
+        let connection = setup_test_db()?;
+
+        let rows: Vec<SerdeRow<Fruit>> = SelectQuery::new("test_table").r#where("id < 2").execute(&connection)?;
+        assert_eq!(rows, vec![
+            SerdeRow(Fruit { id: 0, name: "Orange".to_string(), optional: Some("Strawberry".to_string()) }),
+            SerdeRow(Fruit { id: 1, name: "Apple".to_string(), optional: None })
+        ]);
+
+        Ok(())
+    }
+
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    struct MissingField {
+        id: u32,
+        colour: String
+    }
+
+    #[test]
+    fn missing_field_is_an_error() -> SealionResult<()> {
+        let connection = setup_test_db()?;
+
+        let result: SealionResult<Vec<SerdeRow<MissingField>>> = SelectQuery::new("test_table").execute(&connection);
+        assert!(result.is_err());
+
+        Ok(())
+    }
+}
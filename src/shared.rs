@@ -0,0 +1,97 @@
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use rusqlite::Connection;
+
+use crate::{DeleteQuery, InsertQuery, Row, RowValues, SealionResult, SelectQuery, UpdateQuery};
+
+/// A connection behind a `Mutex`, so that it can be shared between threads.
+///
+/// `Connection` is `Send` but not `Sync`, so a plain `&Connection` can't be handed to other threads. This
+/// wrapper can be, typically inside an `Arc`. Every operation locks the connection for its duration, so
+/// operations from different threads run one at a time rather than concurrently. This is a simple
+/// alternative to a connection pool when there is little contention. Long-running queries block every
+/// other thread, and `with` holds the lock for the whole closure, including any transaction it runs.
+pub struct SharedConnection {
+    connection: Mutex<Connection>
+}
+
+impl SharedConnection {
+    pub fn new(connection: Connection) -> Self {
+        Self {
+            connection: Mutex::new(connection)
+        }
+    }
+
+    /// Locks the connection until the returned guard is dropped.
+    ///
+    /// A thread panicking while holding the lock doesn't leave the connection unusable, since SQLite rolls
+    /// back whatever the thread was in the middle of, so the lock is taken even if it is poisoned.
+    pub fn lock(&self) -> MutexGuard<'_, Connection> {
+        self.connection.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Locks the connection and runs `f` with it.
+    pub fn with<T, F: FnOnce(&Connection) -> SealionResult<T>>(&self, f: F) -> SealionResult<T> {
+        f(&self.lock())
+    }
+
+    /// Runs `query` and parses every row into `R`. See `SelectQuery::execute`.
+    pub fn select<R: Row>(&self, query: &SelectQuery) -> SealionResult<Vec<R>> {
+        query.execute(self.lock())
+    }
+
+    /// Inserts `value` with `query` and returns the number of rows inserted. See `InsertQuery::execute`.
+    pub fn insert<R: RowValues>(&self, query: &InsertQuery, value: &R) -> SealionResult<usize> {
+        query.execute(self.lock(), value)
+    }
+
+    /// Runs `query` and returns the number of rows changed. See `UpdateQuery::execute`.
+    pub fn update(&self, query: &UpdateQuery) -> SealionResult<usize> {
+        query.execute(self.lock())
+    }
+
+    /// Runs `query` and returns the number of rows removed. See `DeleteQuery::execute`.
+    pub fn delete(&self, query: &DeleteQuery) -> SealionResult<usize> {
+        query.execute(self.lock())
+    }
+
+    /// Returns the wrapped connection.
+    pub fn into_inner(self) -> Connection {
+        self.connection.into_inner().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::thread;
+
+    use crate::{DeleteQuery, InsertQuery, SealionResult, SelectQuery, SharedConnection, UpdateQuery};
+    use crate::tests::{setup_test_db, TestRow};
+
+    #[test]
+    fn share_connection_between_threads() -> SealionResult<()> {
+        let shared = Arc::new(SharedConnection::new(setup_test_db()?));
+
+        let handles: Vec<_> = (10..14)
+            .map(|id| {
+                let shared = Arc::clone(&shared);
+                thread::spawn(move || {
+                    let row = TestRow { id, name: format!("Fruit {}", id), optional: None };
+                    shared.insert(&InsertQuery::new("test_table"), &row)
+                })
+            })
+            .collect();
+        for handle in handles {
+            assert_eq!(handle.join().expect("insert thread panicked")?, 1);
+        }
+
+        assert_eq!(shared.update(UpdateQuery::new("test_table").set("optional", "Lime").r#where("id >= 10"))?, 4);
+        assert_eq!(shared.delete(DeleteQuery::new("test_table").r#where("id = 13"))?, 1);
+
+        let rows: Vec<TestRow> = shared.select(SelectQuery::new("test_table").r#where("optional = 'Lime'"))?;
+        assert_eq!(rows.iter().map(|row| row.id).collect::<Vec<_>>(), vec![10, 11, 12]);
+        assert_eq!(shared.with(|connection| SelectQuery::new("test_table").count(connection))?, 6);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,93 @@
+use std::ops::Deref;
+use rusqlite::Connection;
+
+use crate::{with_transaction, DeleteQuery, InsertQuery, RowValues, SealionResult, SelectQuery, Table};
+
+/// Reads every row of the table of `R`, including soft-deleted ones.
+///
+/// Together with `load_table`, this makes it easy to snapshot a table and restore it later, e.g. for test
+/// fixtures. This is a full scan that bypasses the select hooks, since `load_table` replaces every row of the
+/// table, including those a hook would have filtered out.
+pub fn dump_table<R: Table>(connection: impl Deref<Target = Connection>) -> SealionResult<Vec<R>> {
+    let query = SelectQuery::new(R::table_name());
+    let mut statement = query.prepare_statement_with(&connection, R::columns(), &[])?;
+    let rows = R::from_statement(&mut statement, [])?.collect::<rusqlite::Result<Vec<R>>>()?;
+    Ok(rows)
+}
+
+/// Replaces the contents of the table of `R` with `rows`.
+///
+/// The existing rows are deleted and `rows` inserted in a single transaction, so the table is either fully
+/// restored or left as it was.
+pub fn load_table<R: Table + RowValues>(connection: &mut Connection, rows: &[R]) -> SealionResult<()> {
+    with_transaction(connection, |connection| {
+        DeleteQuery::new(R::table_name()).execute(connection)?;
+
+        let mut insert = InsertQuery::for_table::<R>().prepare::<R>(connection)?;
+        for row in rows {
+            insert.push(row)?;
+        }
+
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{add_select_hook, dump_table, load_table, Condition, Row, SealionResult, Table};
+    use crate::tests::{setup_test_db, TestRow};
+
+    #[test]
+    fn dump_and_restore_table() -> SealionResult<()> {
+        let mut connection = setup_test_db()?;
+
+        let snapshot: Vec<TestRow> = dump_table(&connection)?;
+        assert_eq!(snapshot.len(), 3);
+
+        connection.execute_batch("DELETE FROM test_table WHERE id = 0; UPDATE test_table SET name = 'Pear' WHERE id = 2;")?;
+        load_table(&mut connection, &snapshot)?;
+        assert_eq!(dump_table::<TestRow>(&connection)?, snapshot);
+
+        // A failed load leaves the table untouched
+        let duplicates = vec![snapshot[0].clone(), snapshot[0].clone()];
+        assert!(load_table(&mut connection, &duplicates).is_err());
+        assert_eq!(dump_table::<TestRow>(&connection)?, snapshot);
+
+        Ok(())
+    }
+
+    #[test]
+    fn dump_ignores_hooks() -> SealionResult<()> {
+        #[derive(Row, Debug, PartialEq, Clone)]
+        struct SnapshotRow {
+            id: u64,
+            name: String
+        }
+
+        impl Table for SnapshotRow {
+            type Key = u64;
+
+            fn table_name<'a>() -> &'a str {
+                "hooked_snapshot"
+            }
+        }
+
+        let mut connection = setup_test_db()?;
+        connection.execute_batch("ALTER TABLE test_table RENAME TO hooked_snapshot")?;
+
+        // Hooks are global, so only the table used by this test is restricted
+        add_select_hook(|query| match query.table_name.as_str() {
+            "hooked_snapshot" => vec![Condition::eq("id", 1)],
+            _ => Vec::new()
+        });
+
+        let snapshot: Vec<SnapshotRow> = dump_table(&connection)?;
+        assert_eq!(snapshot.len(), 3);
+
+        load_table(&mut connection, &snapshot)?;
+        let count: u64 = connection.query_row("SELECT COUNT(*) FROM hooked_snapshot", [], |row| row.get(0))?;
+        assert_eq!(count, 3);
+
+        Ok(())
+    }
+}
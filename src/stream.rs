@@ -0,0 +1,75 @@
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use futures_core::Stream;
+use rusqlite::Connection;
+use tokio::sync::mpsc;
+
+use crate::{Row, SealionResult, SelectQuery};
+
+/// The rows of a query read by a blocking task. See `SelectQuery::execute_stream`.
+pub struct RowStream<R> {
+    receiver: mpsc::Receiver<SealionResult<R>>
+}
+
+impl<R> Stream for RowStream<R> {
+    type Item = SealionResult<R>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.receiver.poll_recv(cx)
+    }
+}
+
+impl SelectQuery {
+    /// Like `execute_channel`, but reads the rows on a blocking task of the Tokio runtime with
+    /// `spawn_blocking`, and returns them as a `Stream` for async code.
+    ///
+    /// At most `capacity` rows are buffered, and a capacity of 0 is treated as 1. The task waits whenever the
+    /// buffer is full, and stops once the stream is dropped. This must be called from within a Tokio runtime.
+    pub fn execute_stream<R: Row + Send + 'static>(&self, connection: Connection, capacity: usize) -> RowStream<R> {
+        let (sender, receiver) = mpsc::channel(capacity.max(1));
+        let send_rows = self.row_sender::<R>();
+        tokio::task::spawn_blocking(move || send_rows(&connection, &mut |row| sender.blocking_send(row).is_ok()));
+        RowStream { receiver }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future::poll_fn;
+    use std::pin::Pin;
+    use futures_core::Stream;
+
+    use crate::{Condition, RowStream, SealionError, SealionResult, SelectQuery};
+    use crate::tests::{setup_test_db, TestRow};
+
+    async fn next(stream: &mut RowStream<TestRow>) -> Option<SealionResult<TestRow>> {
+        poll_fn(|cx| Pin::new(&mut *stream).poll_next(cx)).await
+    }
+
+    #[tokio::test]
+    async fn rows_are_streamed() -> SealionResult<()> {
+        let connection = setup_test_db()?;
+        connection.execute_batch("
+            WITH RECURSIVE ids(id) AS (SELECT 3 UNION ALL SELECT id + 1 FROM ids WHERE id < 99)
+            INSERT INTO test_table (id, name) SELECT id, 'Fruit' FROM ids;
+        ")?;
+
+        let mut query = SelectQuery::new("test_table");
+        query.and_where(Condition::eq("name", "Fruit"));
+        let mut stream = query.execute_stream(connection, 4);
+
+        let mut ids = Vec::new();
+        while let Some(row) = next(&mut stream).await {
+            ids.push(row?.id);
+        }
+        assert_eq!(ids, (3..100).collect::<Vec<_>>());
+
+        let connection = setup_test_db()?;
+        connection.execute("UPDATE test_table SET id = -1 WHERE name = 'Apple'", [])?;
+        let mut stream = SelectQuery::new("test_table").execute_stream(connection, 0);
+        assert!(matches!(next(&mut stream).await, Some(Err(SealionError::ParseError { row: 0, .. }))));
+        assert!(next(&mut stream).await.is_none());
+
+        Ok(())
+    }
+}
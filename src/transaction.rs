@@ -0,0 +1,226 @@
+use rusqlite::{Connection, TransactionBehavior};
+
+use crate::{total_changes, Row, SealionResult, SelectQuery};
+
+/// Runs the reads in `f` inside a deferred transaction, so they all see the same snapshot of the database.
+///
+/// Without a transaction, every statement sees the latest committed data, so related queries in a report
+/// can disagree if a write lands between them. A read transaction pins the snapshot at its first read.
+/// In WAL mode writers carry on while the snapshot is held, in rollback journal mode the held shared lock
+/// blocks writers from committing until `f` returns.
+///
+/// The transaction is committed if `f` succeeds and rolled back otherwise.
+pub fn with_read_snapshot<T>(connection: &mut Connection, f: impl FnOnce(&Connection) -> SealionResult<T>) -> SealionResult<T> {
+    with_transaction_behavior(connection, TransactionBehavior::Deferred, f)
+}
+
+/// Runs `f` inside a deferred transaction, committing it if `f` succeeds and rolling it back otherwise.
+pub fn with_transaction<T>(connection: &mut Connection, f: impl FnOnce(&Connection) -> SealionResult<T>) -> SealionResult<T> {
+    with_transaction_behavior(connection, TransactionBehavior::Deferred, f)
+}
+
+/// Like `with_transaction`, but also returns the total number of rows changed inside the transaction.
+///
+/// The count is the difference in `total_changes` from before to after `f`, so it includes every
+/// INSERT, UPDATE and DELETE made by `f` as well as the rows changed by triggers.
+pub fn with_counted_transaction<T>(connection: &mut Connection, f: impl FnOnce(&Connection) -> SealionResult<T>) -> SealionResult<(T, usize)> {
+    with_transaction(connection, |transaction| {
+        let before = total_changes(transaction)?;
+        let result = f(transaction)?;
+        Ok((result, (total_changes(transaction)? - before) as usize))
+    })
+}
+
+/// Like `with_transaction`, but defers foreign key checks until the transaction commits.
+///
+/// This sets `PRAGMA defer_foreign_keys`, which SQLite turns off again when the transaction ends. Rows
+/// referring to each other can then be inserted in any order, as long as every reference is satisfied by
+/// the time `f` returns. A violation makes the commit fail with a `SealionError`, rolling the transaction
+/// back. Foreign keys are only enforced at all once `PRAGMA foreign_keys` is on.
+pub fn with_deferred_foreign_keys<T>(connection: &mut Connection, f: impl FnOnce(&Connection) -> SealionResult<T>) -> SealionResult<T> {
+    with_transaction(connection, |transaction| {
+        transaction.pragma_update(None, "defer_foreign_keys", true)?;
+        f(transaction)
+    })
+}
+
+/// Like `with_transaction`, but with the given behavior.
+///
+/// A deferred transaction only takes a lock when it first reads or writes, so two deferred transactions
+/// that read and then write can deadlock, with one failing as busy. `TransactionBehavior::Immediate` takes
+/// the write lock up front instead, so writers queue up at the start of the transaction.
+pub fn with_transaction_behavior<T>(connection: &mut Connection, behavior: TransactionBehavior, f: impl FnOnce(&Connection) -> SealionResult<T>) -> SealionResult<T> {
+    let transaction = connection.transaction_with_behavior(behavior)?;
+    let result = f(&transaction)?;
+    transaction.commit()?;
+    Ok(result)
+}
+
+impl SelectQuery {
+    /// Selects rows for a read-modify-write, emulating `SELECT ... FOR UPDATE`.
+    ///
+    /// SQLite has no row-level locks, so this is only an emulation: the query runs in an IMMEDIATE
+    /// transaction, which takes the database's write lock before reading. No other connection can write
+    /// until `f` returns, so the rows can't change between being read and being written back. Note that
+    /// this locks the whole database rather than just the selected rows, so `f` should be brief.
+    ///
+    /// `f` is given the transaction and the selected rows. The transaction is committed if `f` succeeds
+    /// and rolled back otherwise.
+    pub fn execute_for_update<R: Row, T>(&self, connection: &mut Connection, f: impl FnOnce(&Connection, Vec<R>) -> SealionResult<T>) -> SealionResult<T> {
+        with_transaction_behavior(connection, TransactionBehavior::Immediate, |transaction| {
+            let rows = self.execute(transaction)?;
+            f(transaction, rows)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use rusqlite::{Connection, TransactionBehavior};
+
+    use crate::{set_busy_timeout, with_counted_transaction, with_deferred_foreign_keys, with_read_snapshot, with_transaction, with_transaction_behavior, Condition, SealionError, SealionResult, SelectQuery, UpdateQuery};
+    use crate::tests::{setup_test_db, TempDatabase, TestRow};
+
+    #[test]
+    fn read_snapshot_runs_reads() -> SealionResult<()> {
+        let mut connection = setup_test_db()?;
+
+        let (rows, count) = with_read_snapshot(&mut connection, |connection| {
+            let rows: Vec<TestRow> = SelectQuery::new("test_table").where_null("optional").execute(connection)?;
+            let count = SelectQuery::new("test_table").count(connection)?;
+            Ok((rows, count))
+        })?;
+        assert_eq!(rows, vec![TestRow { id: 1, name: "Apple".to_string(), optional: None }]);
+        assert_eq!(count, 3);
+        assert!(connection.is_autocommit());
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_snapshot_ignores_concurrent_writes() -> SealionResult<()> {
+        let database = TempDatabase::new("snapshot");
+        let mut connection = Connection::open(&database.path)?;
+        connection.pragma_update(None, "journal_mode", "WAL")?;
+        connection.execute("CREATE TABLE test_table (id INTEGER PRIMARY KEY, name TEXT NOT NULL, optional TEXT)", [])?;
+        let writer = Connection::open(&database.path)?;
+
+        let counts = with_read_snapshot(&mut connection, |connection| {
+            let before = SelectQuery::new("test_table").count(connection)?;
+            writer.execute(r#"INSERT INTO test_table (id, name) VALUES (0, "Orange")"#, [])?;
+            let after = SelectQuery::new("test_table").count(connection)?;
+            Ok((before, after))
+        })?;
+        assert_eq!(counts, (0, 0));
+        assert_eq!(SelectQuery::new("test_table").count(&connection)?, 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn transaction_rolls_back_on_error() -> SealionResult<()> {
+        let mut connection = setup_test_db()?;
+
+        let result: SealionResult<()> = with_transaction(&mut connection, |connection| {
+            connection.execute("DELETE FROM test_table", [])?;
+            Err(SealionError::InvalidQuery("abort".to_string()))
+        });
+        assert!(result.is_err());
+        assert_eq!(SelectQuery::new("test_table").count(&connection)?, 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn counted_transaction_totals_changes() -> SealionResult<()> {
+        let mut connection = setup_test_db()?;
+
+        let ((), changed) = with_counted_transaction(&mut connection, |connection| {
+            UpdateQuery::new("test_table").set("optional", "Cherry").execute(connection)?;
+            connection.execute("DELETE FROM test_table WHERE id = 0", [])?;
+            Ok(())
+        })?;
+        assert_eq!(changed, 4);
+
+        Ok(())
+    }
+
+    #[test]
+    fn immediate_transaction_locks_out_writers() -> SealionResult<()> {
+        let database = TempDatabase::new("immediate");
+        let mut connection = Connection::open(&database.path)?;
+        connection.pragma_update(None, "journal_mode", "WAL")?;
+        connection.execute("CREATE TABLE test_table (id INTEGER PRIMARY KEY, name TEXT NOT NULL, optional TEXT)", [])?;
+        let writer = Connection::open(&database.path)?;
+        set_busy_timeout(&writer, Duration::ZERO)?;
+
+        with_transaction_behavior(&mut connection, TransactionBehavior::Immediate, |_| {
+            let result = writer.execute(r#"INSERT INTO test_table (id, name) VALUES (0, "Orange")"#, []);
+            assert!(matches!(result.map_err(SealionError::from), Err(SealionError::Busy(_))));
+            Ok(())
+        })?;
+
+        writer.execute(r#"INSERT INTO test_table (id, name) VALUES (0, "Orange")"#, [])?;
+        assert_eq!(SelectQuery::new("test_table").count(&connection)?, 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_for_update_blocks_writers() -> SealionResult<()> {
+        let database = TempDatabase::new("for_update");
+        let mut connection = Connection::open(&database.path)?;
+        connection.execute_batch("
+            CREATE TABLE test_table (id INTEGER PRIMARY KEY, name TEXT NOT NULL, optional TEXT);
+            INSERT INTO test_table (id, name) VALUES (1, 'Apple');
+        ")?;
+        let writer = Connection::open(&database.path)?;
+        set_busy_timeout(&writer, Duration::ZERO)?;
+
+        let name = SelectQuery::new("test_table").where_eq("id", 1).execute_for_update(&mut connection, |transaction, rows: Vec<TestRow>| {
+            let result = writer.execute("UPDATE test_table SET name = 'Banana' WHERE id = 1", []);
+            assert!(matches!(result.map_err(SealionError::from), Err(SealionError::Busy(_))));
+
+            let name = format!("{} pie", rows[0].name);
+            UpdateQuery::new("test_table").set("name", name.clone()).and_where(Condition::eq("id", 1)).execute(transaction)?;
+            Ok(name)
+        })?;
+        assert_eq!(name, "Apple pie");
+
+        let rows: Vec<TestRow> = SelectQuery::new("test_table").execute(&connection)?;
+        assert_eq!(rows[0].name, "Apple pie");
+
+        Ok(())
+    }
+
+    #[test]
+    fn deferred_foreign_keys_are_checked_at_commit() -> SealionResult<()> {
+        let mut connection = Connection::open_in_memory()?;
+        connection.execute_batch("
+            PRAGMA foreign_keys = ON;
+            CREATE TABLE parent (id INTEGER PRIMARY KEY);
+            CREATE TABLE child (id INTEGER PRIMARY KEY, parent_id INTEGER NOT NULL REFERENCES parent (id));
+        ")?;
+
+        with_deferred_foreign_keys(&mut connection, |connection| {
+            connection.execute("INSERT INTO child VALUES (1, 1)", [])?;
+            connection.execute("INSERT INTO parent VALUES (1)", [])?;
+            Ok(())
+        })?;
+        assert_eq!(SelectQuery::new("child").count(&connection)?, 1);
+
+        let result = with_deferred_foreign_keys(&mut connection, |connection| {
+            connection.execute("INSERT INTO child VALUES (2, 2)", [])?;
+            Ok(())
+        });
+        assert!(matches!(result, Err(SealionError::RusqliteError(_))));
+        assert!(connection.is_autocommit());
+        assert_eq!(SelectQuery::new("child").count(&connection)?, 1);
+
+        // Checks are immediate again outside of the transaction
+        assert!(connection.execute("INSERT INTO child VALUES (3, 3)", []).is_err());
+
+        Ok(())
+    }
+}
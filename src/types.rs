@@ -0,0 +1,266 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use rusqlite::ToSql;
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSqlOutput, ValueRef};
+
+use crate::column_parse_error;
+
+/// 2^63, the smallest float that is too large for an `i64`.
+const I64_LIMIT: f64 = 9_223_372_036_854_775_808.0;
+
+/// A boolean stored as an integer, which only accepts values that are clearly true or false.
+///
+/// Reading a plain `bool` treats any non-zero integer as true, which hides bad data such as a `2` coming
+/// from an external source. `SqlBool` accepts `0` and `1`, as well as the text `true`, `false`, `0` and
+/// `1` in any case, and fails to convert anything else. It's always written as `0` or `1`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct SqlBool(pub bool);
+
+impl From<bool> for SqlBool {
+    fn from(value: bool) -> Self {
+        SqlBool(value)
+    }
+}
+
+impl From<SqlBool> for bool {
+    fn from(value: SqlBool) -> Self {
+        value.0
+    }
+}
+
+impl FromSql for SqlBool {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        match value {
+            ValueRef::Integer(0) => Ok(SqlBool(false)),
+            ValueRef::Integer(1) => Ok(SqlBool(true)),
+            ValueRef::Integer(i) => Err(FromSqlError::OutOfRange(i)),
+            ValueRef::Text(text) => match text.to_ascii_lowercase().as_slice() {
+                b"0" | b"false" => Ok(SqlBool(false)),
+                b"1" | b"true" => Ok(SqlBool(true)),
+                _ => Err(FromSqlError::InvalidType)
+            },
+            _ => Err(FromSqlError::InvalidType)
+        }
+    }
+}
+
+impl ToSql for SqlBool {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.0 as i64))
+    }
+}
+
+const NANOS_PER_SECOND: i128 = 1_000_000_000;
+const NANOS_PER_MILLI: i128 = 1_000_000;
+
+/// A point in time stored as an integer number of seconds since the Unix epoch.
+///
+/// Times between whole seconds are rounded down when written, and times before the epoch are stored as
+/// negative numbers. Use `UnixTimestampMillis` to keep millisecond precision instead. Writing fails for
+/// times too far from the epoch to fit in an `i64`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct UnixTimestamp(pub SystemTime);
+
+/// Like `UnixTimestamp`, but stored as an integer number of milliseconds since the Unix epoch.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct UnixTimestampMillis(pub SystemTime);
+
+impl From<SystemTime> for UnixTimestamp {
+    fn from(time: SystemTime) -> Self {
+        UnixTimestamp(time)
+    }
+}
+
+impl From<SystemTime> for UnixTimestampMillis {
+    fn from(time: SystemTime) -> Self {
+        UnixTimestampMillis(time)
+    }
+}
+
+impl FromSql for UnixTimestamp {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        from_unix(value, NANOS_PER_SECOND).map(UnixTimestamp)
+    }
+}
+
+impl ToSql for UnixTimestamp {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(to_unix(self.0, NANOS_PER_SECOND)?))
+    }
+}
+
+impl FromSql for UnixTimestampMillis {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        from_unix(value, NANOS_PER_MILLI).map(UnixTimestampMillis)
+    }
+}
+
+impl ToSql for UnixTimestampMillis {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(to_unix(self.0, NANOS_PER_MILLI)?))
+    }
+}
+
+/// Converts `time` to the number of units of `unit_nanos` nanoseconds since the epoch, rounding down.
+fn to_unix(time: SystemTime, unit_nanos: i128) -> rusqlite::Result<i64> {
+    let nanos = match time.duration_since(UNIX_EPOCH) {
+        Ok(after) => after.as_nanos() as i128,
+        Err(before) => -(before.duration().as_nanos() as i128)
+    };
+    i64::try_from(nanos.div_euclid(unit_nanos)).map_err(|err| rusqlite::Error::ToSqlConversionFailure(Box::new(err)))
+}
+
+/// Converts a number of units of `unit_nanos` nanoseconds since the epoch back to a `SystemTime`.
+fn from_unix(value: ValueRef<'_>, unit_nanos: i128) -> FromSqlResult<SystemTime> {
+    let units = value.as_i64()?;
+    let nanos = (units as i128 * unit_nanos).unsigned_abs();
+    let duration = Duration::new((nanos / NANOS_PER_SECOND as u128) as u64, (nanos % NANOS_PER_SECOND as u128) as u32);
+
+    let time = if units >= 0 { UNIX_EPOCH.checked_add(duration) } else { UNIX_EPOCH.checked_sub(duration) };
+    time.ok_or(FromSqlError::OutOfRange(units))
+}
+
+/// Reads the column called `name` from `row` as an `f64`, whether it's stored as a real or an integer.
+///
+/// A column's affinity decides how a number is stored, regardless of how it was written: `NUMERIC` and
+/// `INTEGER` columns store `1.0` as the integer `1`, while `REAL` columns store `1` as `1.0`. Reading an `f64`
+/// already accepts integers, but silently rounds those that are too large to represent exactly. This fails
+/// instead, as well as for NULLs, text and blobs. Errors surface as `SealionError::ColumnParse`.
+pub fn get_f64_coerce(row: &rusqlite::Row, name: &str) -> rusqlite::Result<f64> {
+    let index = row.as_ref().column_index(name)?;
+    let coerced = match row.get_ref(index)? {
+        ValueRef::Real(real) => Ok(real),
+        ValueRef::Integer(integer) => {
+            let real = integer as f64;
+            if real < I64_LIMIT && real as i64 == integer {
+                Ok(real)
+            } else {
+                Err(lossy_coercion(index, ValueRef::Integer(integer), integer, "f64"))
+            }
+        },
+        value => Err(rusqlite::Error::InvalidColumnType(index, name.to_string(), value.data_type()))
+    };
+    coerced.map_err(|err| column_parse_error(row, index, name, "f64", err))
+}
+
+/// Reads the column called `name` from `row` as an `i64`, whether it's stored as an integer or a real.
+///
+/// Reals are only accepted if they are whole numbers within the range of an `i64`, such as the `2.0` a
+/// `REAL` column stores for `2`. Reading an `i64` directly fails for every real. This fails for reals with a
+/// fractional part or out of range, as well as for NULLs, text and blobs. Errors surface as
+/// `SealionError::ColumnParse`.
+pub fn get_i64_coerce(row: &rusqlite::Row, name: &str) -> rusqlite::Result<i64> {
+    let index = row.as_ref().column_index(name)?;
+    let coerced = match row.get_ref(index)? {
+        ValueRef::Integer(integer) => Ok(integer),
+        ValueRef::Real(real) if real.fract() == 0.0 && (-I64_LIMIT..I64_LIMIT).contains(&real) => Ok(real as i64),
+        ValueRef::Real(real) => Err(lossy_coercion(index, ValueRef::Real(real), real, "i64")),
+        value => Err(rusqlite::Error::InvalidColumnType(index, name.to_string(), value.data_type()))
+    };
+    coerced.map_err(|err| column_parse_error(row, index, name, "i64", err))
+}
+
+fn lossy_coercion(index: usize, value: ValueRef, number: impl std::fmt::Display, target: &str) -> rusqlite::Error {
+    rusqlite::Error::FromSqlConversionFailure(
+        index,
+        value.data_type(),
+        format!("{} can't be represented exactly as {}", number, target).into()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+    use rusqlite::Connection;
+
+    use crate::{get_f64_coerce, get_i64_coerce, SealionError, SealionResult, SqlBool, UnixTimestamp, UnixTimestampMillis};
+
+    fn read<T: rusqlite::types::FromSql>(connection: &Connection, sql: &str) -> SealionResult<T> {
+        connection.query_row(sql, [], |row| row.get(0)).map_err(SealionError::from)
+    }
+
+    #[test]
+    fn plain_bools_are_integers() -> SealionResult<()> {
+        let connection = Connection::open_in_memory()?;
+
+        assert!(!read::<bool>(&connection, "SELECT 0")?);
+        assert!(read::<bool>(&connection, "SELECT 1")?);
+        // Any non-zero integer reads as true
+        assert!(read::<bool>(&connection, "SELECT 2")?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn sql_bools_reject_ambiguous_values() -> SealionResult<()> {
+        let connection = Connection::open_in_memory()?;
+
+        assert_eq!(read::<SqlBool>(&connection, "SELECT 0")?, SqlBool(false));
+        assert_eq!(read::<SqlBool>(&connection, "SELECT 1")?, SqlBool(true));
+        assert_eq!(read::<SqlBool>(&connection, "SELECT 'TRUE'")?, SqlBool(true));
+        assert_eq!(read::<SqlBool>(&connection, "SELECT 'false'")?, SqlBool(false));
+        assert!(read::<SqlBool>(&connection, "SELECT 2").is_err());
+        assert!(read::<SqlBool>(&connection, "SELECT 'yes'").is_err());
+        assert!(read::<SqlBool>(&connection, "SELECT NULL").is_err());
+
+        let stored: i64 = connection.query_row("SELECT ?", [SqlBool(true)], |row| row.get(0))?;
+        assert_eq!(stored, 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn numbers_follow_column_affinity() -> SealionResult<()> {
+        let connection = Connection::open_in_memory()?;
+        connection.execute_batch("
+            CREATE TABLE numbers (numeric NUMERIC, integer INTEGER, real REAL);
+            INSERT INTO numbers VALUES (1.0, 2.0, 3), (1.5, 2.5, 9223372036854775807);
+        ")?;
+
+        let whole = "SELECT numeric, integer, real FROM numbers WHERE rowid = 1";
+        // Whole reals are stored as integers, except in REAL columns
+        let types: (String, String, String) = connection.query_row(
+            "SELECT typeof(numeric), typeof(integer), typeof(real) FROM numbers WHERE rowid = 1", [], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+        assert_eq!(types, ("integer".to_string(), "integer".to_string(), "real".to_string()));
+        // Reading an f64 accepts integers, but reading an i64 rejects every real
+        assert_eq!(read::<f64>(&connection, whole)?, 1.0);
+        assert!(read::<i64>(&connection, "SELECT real FROM numbers WHERE rowid = 1").is_err());
+
+        let (numeric, real) = connection.query_row(whole, [], |row| Ok((get_f64_coerce(row, "numeric")?, get_i64_coerce(row, "real")?)))?;
+        assert_eq!((numeric, real), (1.0, 3));
+
+        let fraction = connection.query_row("SELECT numeric FROM numbers WHERE rowid = 2", [], |row| get_i64_coerce(row, "numeric"));
+        assert!(matches!(SealionError::from(fraction.unwrap_err()), SealionError::ColumnParse(err) if err.column == "numeric" && err.expected == "i64"));
+
+        // i64::MAX stored in a REAL column is rounded to 2^63 when it is written, which is out of range
+        assert!(connection.query_row("SELECT real FROM numbers WHERE rowid = 2", [], |row| get_i64_coerce(row, "real")).is_err());
+        assert!(connection.query_row("SELECT 9223372036854775807 AS big", [], |row| get_f64_coerce(row, "big")).is_err());
+        assert_eq!(connection.query_row("SELECT 9007199254740992 AS exact", [], |row| get_f64_coerce(row, "exact"))?, 9007199254740992.0);
+        assert!(connection.query_row("SELECT 'text' AS text", [], |row| get_f64_coerce(row, "text")).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn unix_timestamps_round_trip() -> SealionResult<()> {
+        let connection = Connection::open_in_memory()?;
+        let time = UNIX_EPOCH + Duration::from_millis(1_660_000_000_123);
+
+        let seconds: i64 = connection.query_row("SELECT ?", [UnixTimestamp(time)], |row| row.get(0))?;
+        assert_eq!(seconds, 1_660_000_000);
+        let millis: i64 = connection.query_row("SELECT ?", [UnixTimestampMillis(time)], |row| row.get(0))?;
+        assert_eq!(millis, 1_660_000_000_123);
+
+        assert_eq!(read::<UnixTimestampMillis>(&connection, "SELECT 1660000000123")?, UnixTimestampMillis(time));
+        assert_eq!(read::<UnixTimestamp>(&connection, "SELECT 1660000000")?, UnixTimestamp(UNIX_EPOCH + Duration::from_secs(1_660_000_000)));
+
+        // Times before the epoch round down to the previous second
+        let before = UNIX_EPOCH - Duration::from_millis(1500);
+        let seconds: i64 = connection.query_row("SELECT ?", [UnixTimestamp(before)], |row| row.get(0))?;
+        assert_eq!(seconds, -2);
+        assert_eq!(read::<UnixTimestampMillis>(&connection, "SELECT -1500")?, UnixTimestampMillis(before));
+
+        assert!(read::<UnixTimestamp>(&connection, "SELECT '2022-08-08'").is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,369 @@
+use std::collections::HashMap;
+use std::ops::Deref;
+use std::time::Duration;
+use rusqlite::{CachedStatement, Connection, Params, ToSql, params_from_iter};
+
+use crate::cache::prepare_cached;
+use crate::{bind_named_params, quote_ident, retry_busy, to_value, write_where_clause, Condition, RowValues, SealionError, SealionResult, Table};
+
+/// A `column = expression` pair in the SET clause of an update, along with the parameters bound by
+/// the expression.
+pub struct Assignment {
+    pub column: String,
+    pub expression: String,
+    pub params: Vec<Box<dyn ToSql>>
+}
+
+pub struct UpdateQuery {
+    pub table_name: String,
+    pub assignments: Vec<Assignment>,
+    pub where_clause: Option<String>,
+    pub conditions: Vec<Condition>
+}
+
+impl UpdateQuery {
+    pub fn new<S: ToString>(table_name: S) -> Self {
+        Self {
+            table_name: table_name.to_string(),
+            assignments: Vec::new(),
+            where_clause: None,
+            conditions: Vec::new()
+        }
+    }
+
+    /// Builds an update writing every column of `value` to the row with the same primary key.
+    ///
+    /// This renders as `UPDATE t SET col = ?, ... WHERE pk = ?`, with each placeholder bound to the field of
+    /// the same column. Binding by position rather than by name keeps columns whose names aren't valid
+    /// parameter names, such as `my-col`, working.
+    ///
+    /// Fails with `SealionError::UnknownColumns` if a primary key column isn't one of the columns of `R`, rather
+    /// than updating every row.
+    pub fn set_all<R: Table + RowValues>(value: &R) -> SealionResult<Self> {
+        let mut query = Self::new(R::table_name());
+        let primary_key = R::primary_key();
+
+        let unknown_columns: Vec<String> = primary_key
+            .iter()
+            .filter(|key_column| !R::columns().contains(key_column))
+            .map(|key_column| key_column.to_string())
+            .collect();
+        if !unknown_columns.is_empty() {
+            return Err(SealionError::UnknownColumns(unknown_columns));
+        }
+
+        for (column, param) in R::columns().iter().zip(value.to_params()) {
+            let value = Box::new(to_value(param)?);
+
+            if primary_key.contains(column) {
+                query.and_where(Condition {
+                    sql: format!("{} = ?", quote_ident(column)),
+                    params: vec![value]
+                });
+            } else {
+                query.assignments.push(Assignment {
+                    column: column.to_string(),
+                    expression: "?".to_string(),
+                    params: vec![value]
+                });
+            }
+        }
+
+        Ok(query)
+    }
+
+    /// Builds an update that only sets the columns whose values differ between `old` and `new`.
+    ///
+    /// Leaving unchanged columns alone avoids rewriting them, and avoids clobbering a column that was
+    /// changed concurrently by someone else. The query has no WHERE clause, so should be narrowed down
+    /// to the row being updated.
+    pub fn changes<R: Table + RowValues>(old: &R, new: &R) -> SealionResult<Self> {
+        let mut query = Self::new(R::table_name());
+
+        for ((column, old_value), new_value) in R::columns().iter().zip(old.values()?).zip(new.values()?) {
+            if old_value != new_value {
+                query.set(column, new_value);
+            }
+        }
+
+        Ok(query)
+    }
+
+    /// Sets `column` to `value`, which is bound as a parameter.
+    pub fn set<S: ToString, T: ToSql + 'static>(&mut self, column: S, value: T) -> &mut Self {
+        self.assignments.push(Assignment {
+            column: column.to_string(),
+            expression: "?".to_string(),
+            params: vec![Box::new(value)]
+        });
+        self
+    }
+
+    /// Sets `column` to a placeholder whose value is bound later, see `prepare`.
+    pub fn set_param<S: ToString>(&mut self, column: S) -> &mut Self {
+        self.set_expr(column, "?")
+    }
+
+    /// Sets `column` to the result of the raw SQL `expression`, e.g. `count + 1`.
+    ///
+    /// The expression is embedded in the statement verbatim, so it must never contain untrusted input. Use
+    /// `set` to write values instead.
+    pub fn set_expr<S: ToString, E: ToString>(&mut self, column: S, expression: E) -> &mut Self {
+        self.assignments.push(Assignment {
+            column: column.to_string(),
+            expression: expression.to_string(),
+            params: Vec::new()
+        });
+        self
+    }
+
+    pub fn r#where<S: ToString>(&mut self, where_clause: S) -> &mut Self {
+        self.where_clause = Some(where_clause.to_string());
+        self
+    }
+
+    /// Same as `r#where`, without needing the raw identifier.
+    pub fn filter<S: ToString>(&mut self, where_clause: S) -> &mut Self {
+        self.r#where(where_clause)
+    }
+
+    /// Adds a condition that updated rows must match, in addition to any other conditions.
+    pub fn and_where(&mut self, condition: Condition) -> &mut Self {
+        self.conditions.push(condition);
+        self
+    }
+
+    pub fn build_sql_string(&self) -> String {
+        let assignments: Vec<String> = self.assignments
+            .iter()
+            .map(|assignment| format!("{} = {}", quote_ident(&assignment.column), assignment.expression))
+            .collect();
+
+        let mut sql_string = format!("UPDATE {} ", self.table_name);
+        sql_string.push_str(&format!("SET {} ", assignments.join(", ")));
+
+        let mut conditions: Vec<String> = self.where_clause.iter().cloned().collect();
+        conditions.extend(self.conditions.iter().map(|condition| condition.sql.clone()));
+        write_where_clause(&mut sql_string, &conditions);
+
+        sql_string
+    }
+
+    /// Returns the parameters bound by the assignments and conditions, in the order they appear in the SQL.
+    fn params(&self) -> impl Iterator<Item = &dyn ToSql> {
+        self.assignments
+            .iter()
+            .flat_map(|assignment| assignment.params.iter())
+            .chain(self.conditions.iter().flat_map(|condition| condition.params.iter()))
+            .map(|param| param.as_ref())
+    }
+
+    /// Like `execute`, but retries up to `attempts` times while the database is busy. See `retry_busy`.
+    pub fn execute_with_retry(&self, connection: impl Deref<Target = Connection>, attempts: u32, backoff: Duration) -> SealionResult<usize> {
+        retry_busy(attempts, backoff, || self.execute(&*connection))
+    }
+
+    /// Runs the update and returns the number of rows changed.
+    /// An update without any assignments does nothing and returns 0.
+    pub fn execute(&self, connection: impl Deref<Target = Connection>) -> SealionResult<usize> {
+        if self.assignments.is_empty() {
+            return Ok(0);
+        }
+
+        let mut statement = prepare_cached(&connection, &self.build_sql_string())?;
+        let rows_changed = statement.execute(params_from_iter(self.params()))?;
+
+        Ok(rows_changed)
+    }
+
+    /// Like `execute`, but binds the named parameters in the SQL, such as `:name`, to the values in `named`.
+    /// See `SelectQuery::execute_named`.
+    pub fn execute_named(&self, connection: impl Deref<Target = Connection>, named: &HashMap<String, Box<dyn ToSql>>) -> SealionResult<usize> {
+        if self.assignments.is_empty() {
+            return Ok(0);
+        }
+
+        let mut statement = prepare_cached(&connection, &self.build_sql_string())?;
+        bind_named_params(&mut statement, self.params().collect(), named)?;
+        Ok(statement.raw_execute()?)
+    }
+
+    /// Prepares the update once so that it can be run many times with different parameters.
+    ///
+    /// The statement is built from the query as it is, but the parameters stored in the query are ignored:
+    /// every placeholder, in assignments and conditions alike, is bound by `PreparedUpdate::run`. Use
+    /// `set_param` and placeholders in `r#where` to leave values to be bound. For the best throughput, run
+    /// the updates inside a transaction.
+    pub fn prepare<'conn>(&self, connection: &'conn Connection) -> SealionResult<PreparedUpdate<'conn>> {
+        Ok(PreparedUpdate {
+            statement: prepare_cached(connection, &self.build_sql_string())?,
+            rows_changed: 0
+        })
+    }
+}
+
+/// An update statement that has been prepared to run many times. See `UpdateQuery::prepare`.
+pub struct PreparedUpdate<'conn> {
+    statement: CachedStatement<'conn>,
+    rows_changed: usize
+}
+
+impl<'conn> PreparedUpdate<'conn> {
+    /// Runs the update with `params` bound to its placeholders and returns the number of rows changed.
+    pub fn run<P: Params>(&mut self, params: P) -> SealionResult<usize> {
+        let rows_changed = self.statement.execute(params)?;
+        self.rows_changed += rows_changed;
+        Ok(rows_changed)
+    }
+
+    /// Returns the number of rows changed by every run so far.
+    pub fn rows_changed(&self) -> usize {
+        self.rows_changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rusqlite::{params, Connection};
+
+    use crate::{Condition, Row, SealionError, SealionResult, SelectQuery, Table, UpdateQuery};
+    use crate::tests::{setup_test_db, TestRow};
+
+    #[test]
+    fn update_changed_columns() -> SealionResult<()> {
+        let connection = setup_test_db()?;
+
+        let old = TestRow { id: 1, name: "Apple".to_string(), optional: None };
+        let new = TestRow { id: 1, name: "Apple".to_string(), optional: Some("Blueberry".to_string()) };
+
+        let mut query = UpdateQuery::changes(&old, &new)?;
+        query.r#where("id = 1");
+        assert_eq!(query.build_sql_string(), r#"UPDATE test_table SET "optional" = ? WHERE id = 1 "#);
+        assert_eq!(query.execute(&connection)?, 1);
+
+        let rows: Vec<TestRow> = SelectQuery::new("test_table").filter("id = 1").execute(&connection)?;
+        assert_eq!(rows, vec![new]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn update_with_expression() -> SealionResult<()> {
+        let connection = setup_test_db()?;
+
+        let mut query = UpdateQuery::new("test_table");
+        query.set_expr("id", "id + 10").set("optional", "Cherry").and_where(Condition::eq("name", "Peach"));
+        assert_eq!(query.build_sql_string(), r#"UPDATE test_table SET "id" = id + 10, "optional" = ? WHERE "name" = ? "#);
+        assert_eq!(query.execute(&connection)?, 1);
+
+        let rows: Vec<TestRow> = SelectQuery::new("test_table").where_eq("name", "Peach").execute(&connection)?;
+        assert_eq!(rows, vec![TestRow { id: 12, name: "Peach".to_string(), optional: Some("Cherry".to_string()) }]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn update_many_prepared() -> SealionResult<()> {
+        let mut connection = setup_test_db()?;
+        let transaction = connection.transaction()?;
+
+        {
+            let mut query = UpdateQuery::new("test_table");
+            query.set_param("optional").r#where("id = ?");
+            assert_eq!(query.build_sql_string(), r#"UPDATE test_table SET "optional" = ? WHERE id = ? "#);
+
+            let mut update = query.prepare(&transaction)?;
+            for (id, optional) in [(0, "Cherry"), (1, "Lime"), (5, "Missing")] {
+                update.run(params![optional, id])?;
+            }
+            assert_eq!(update.rows_changed(), 2);
+        }
+        transaction.commit()?;
+
+        let rows: Vec<TestRow> = SelectQuery::new("test_table").where_eq("optional", "Lime").execute(&connection)?;
+        assert_eq!(rows, vec![TestRow { id: 1, name: "Apple".to_string(), optional: Some("Lime".to_string()) }]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn update_without_changes() -> SealionResult<()> {
+        let connection = setup_test_db()?;
+
+        let row = TestRow { id: 1, name: "Apple".to_string(), optional: None };
+        assert_eq!(UpdateQuery::changes(&row, &row)?.execute(&connection)?, 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn update_all_columns_by_primary_key() -> SealionResult<()> {
+        let connection = setup_test_db()?;
+
+        let row = TestRow { id: 2, name: "Nectarine".to_string(), optional: None };
+        let query = UpdateQuery::set_all(&row)?;
+        assert_eq!(query.build_sql_string(), r#"UPDATE test_table SET "name" = ?, "optional" = ? WHERE "id" = ? "#);
+        assert_eq!(query.execute(&connection)?, 1);
+
+        let rows: Vec<TestRow> = SelectQuery::new("test_table").r#where("id = 2").execute(&connection)?;
+        assert_eq!(rows, vec![row]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn update_all_columns_with_unusual_names() -> SealionResult<()> {
+        #[derive(Row)]
+        struct Setting {
+            #[sealion(column = "setting-id")]
+            setting_id: i64,
+            #[sealion(column = "setting-value")]
+            setting_value: String
+        }
+
+        impl Table for Setting {
+            type Key = i64;
+
+            fn table_name<'a>() -> &'a str {
+                "settings"
+            }
+
+            fn primary_key<'a>() -> &'a [&'a str] {
+                &["setting-id"]
+            }
+        }
+
+        let connection = Connection::open_in_memory()?;
+        connection.execute_batch(r#"CREATE TABLE settings ("setting-id" INTEGER PRIMARY KEY, "setting-value" TEXT);
+            INSERT INTO settings VALUES (1, 'light');"#)?;
+
+        let setting = Setting { setting_id: 1, setting_value: "dark".to_string() };
+        assert_eq!(UpdateQuery::set_all(&setting)?.execute(&connection)?, 1);
+
+        let value: String = connection.query_row(r#"SELECT "setting-value" FROM settings WHERE "setting-id" = 1"#, [], |row| row.get(0))?;
+        assert_eq!(value, "dark");
+
+        Ok(())
+    }
+
+    #[test]
+    fn update_all_columns_without_primary_key_column() -> SealionResult<()> {
+        #[derive(Row)]
+        struct Untracked {
+            name: String
+        }
+
+        impl Table for Untracked {
+            type Key = i64;
+
+            fn table_name<'a>() -> &'a str {
+                "test_table"
+            }
+        }
+
+        let result = UpdateQuery::set_all(&Untracked { name: "Plum".to_string() });
+        assert!(matches!(result, Err(SealionError::UnknownColumns(columns)) if columns == vec!["id".to_string()]));
+
+        Ok(())
+    }
+}